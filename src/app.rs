@@ -1,29 +1,101 @@
 use eframe::egui;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::sync::mpsc::{Receiver, channel};
 
 // Internal modules
 use crate::models::{dir_node::DirNode, file_node::FileNode, export_mode::ExportMode, theme::ThemePreference};
-use crate::scanner::{ScanMessage, thread::read_dir_recursive_threaded};
-use crate::operations::{selection, export};
-use crate::ui::{panels, tree};
+use crate::models::export_options::{ExportOptions, SeparateFilesOptions};
+use crate::models::line_range::LineRange;
+use crate::models::profile::{ApplyMode, SelectionEntry, SelectionProfile};
+use crate::models::session::Session;
+use crate::models::export_snapshot::ExportSnapshot;
+use crate::models::export_record::{ExportRecord, ExportTarget};
+use crate::models::recent_project::RecentProject;
+use crate::scanner::{ScanMessage, revision::read_revision_tree, thread::read_dir_recursive_threaded};
+use crate::operations::{selection, backup, export, git, imports, project_store, tracking};
+use crate::operations::export::SelectionEstimate;
+use crate::operations::backup::Snapshot;
+use crate::operations::imports::{ImportGraph, ProjectIndex};
+use crate::operations::tracking::ExportChanges;
+use crate::operations::search::{self, SearchMatches};
+use crate::ui::{apply_response, content_search, export_preview, import_selection, panels, patch_dialog, pattern_dialog, preview, recents, tree};
+use crate::ui::import_selection::ImportSelectionDialog;
+use crate::ui::content_search::ContentSearch;
+use crate::ui::patch_dialog::PatchDialog;
+use crate::ui::apply_response::ApplyResponseDialog;
+use crate::ui::pattern_dialog::PatternDialog;
+use crate::ui::export_preview::ExportPreview;
+use crate::ui::preview::PreviewState;
+use crate::ui::tree::TreeAction;
+
+const PROFILES_FILE: &str = "profiles.json";
+const HISTORY_FILE: &str = "history.json";
+const MAX_HISTORY: usize = 50;
+const MAX_RECENT_PROJECTS: usize = 10;
 
 pub struct CodeCollectorApp {
     pub project_path: Option<PathBuf>,
     pub root_node: Option<DirNode>,
     pub status_text: String,
     pub export_mode: ExportMode,
+    pub separate_options: SeparateFilesOptions,
+    pub export_options: ExportOptions,
     pub theme: ThemePreference,
     pub search_query: String,
+    pub search_matches: Option<(String, SearchMatches)>, // (query it was computed for, matches)
+    pub ranked_view: bool, // Flat list ordered by match score instead of the filtered tree
     pub recent_files: VecDeque<FileNode>,
+    pub recent_projects: Vec<RecentProject>,
+    pub preview: Option<PreviewState>,
+    pub export_preview: Option<ExportPreview>,
+    pub pattern_dialog: Option<PatternDialog>,
+    pub apply_dialog: Option<ApplyResponseDialog>,
+    pub patch_dialog: Option<PatchDialog>,
+    pub import_dialog: Option<ImportSelectionDialog>,
+    pub content_search: Option<ContentSearch>,
+    pub last_snapshot: Option<Snapshot>, // Undo for the last Apply Response / Apply Patch
+    pub git_base_ref: String,
+    pub revision: Option<String>, // Project opened at this git ref instead of the working tree
+    pub revision_input: String,
+
+    // --- SELECTION PROFILES ---
+    pub profiles: Vec<SelectionProfile>,
+    pub active_profile: Option<usize>,
+    pub new_profile_name: String,
+    pub profile_missing: Option<(String, Vec<String>)>, // (profile name, missing paths)
+
+    // --- EXPORT HISTORY (newest first) ---
+    pub history: Vec<ExportRecord>,
+    pub history_profile_name: String,
 
     // --- REFRESH / SYNC STATE ---
-    pub preserved_selections: Option<HashSet<PathBuf>>,
+    pub preserved_selections: Option<HashMap<PathBuf, Vec<LineRange>>>,
+    pub pending_session: Option<Session>,
     pub show_missing_files_alert: bool,
+    pub pending_clean_target: Option<PathBuf>,
+    pub pending_rerun: Option<usize>, // History entry waiting for the user to confirm the re-run
+
+    // --- STATS CACHE ---
+    // Recomputed in the background after selection or export option changes
+    pub token_estimate: Option<usize>, // None while computing
+    pub token_estimate_dirty: bool,
+    pub token_loader: Option<Receiver<SelectionEstimate>>,
+    pub truncated_files: HashSet<String>, // Selected files truncation cuts, from the last estimate
+
+    // --- IMPORT GRAPH (built in the background after each scan) ---
+    pub import_graph: Option<ImportGraph>,
+    pub import_loader: Option<Receiver<ImportGraph>>,
+
+    // --- CHANGE TRACKING (hashes of the last export, compared after each scan) ---
+    pub last_export: Option<ExportSnapshot>,
+    pub export_changes: ExportChanges,
+    pub tracking_loader: Option<Receiver<ExportChanges>>, // Compare running in the background
+    pub delta_loader: Option<Receiver<tracking::Delta>>,
 
     // --- LOADING STATE ---
     pub is_loading: bool,
@@ -39,12 +111,52 @@ impl Default for CodeCollectorApp {
             root_node: None,
             status_text: String::from("Ready to scan."),
             export_mode: ExportMode::OneFile,
+            separate_options: SeparateFilesOptions::default(),
+            export_options: ExportOptions::default(),
             theme: ThemePreference::System,
             search_query: String::new(),
+            search_matches: None,
+            ranked_view: false,
             recent_files: VecDeque::with_capacity(3),
+            recent_projects: Vec::new(),
+            preview: None,
+            export_preview: None,
+            pattern_dialog: None,
+            apply_dialog: None,
+            patch_dialog: None,
+            import_dialog: None,
+            content_search: None,
+            last_snapshot: None,
+            git_base_ref: "main".to_string(),
+            revision: None,
+            revision_input: String::new(),
             
+            profiles: Vec::new(),
+            active_profile: None,
+            new_profile_name: String::new(),
+            profile_missing: None,
+
+            history: Vec::new(),
+            history_profile_name: String::new(),
+
             preserved_selections: None,
+            pending_session: None,
             show_missing_files_alert: false,
+            pending_clean_target: None,
+            pending_rerun: None,
+
+            token_estimate: None,
+            token_estimate_dirty: false,
+            token_loader: None,
+            truncated_files: HashSet::new(),
+
+            import_graph: None,
+            import_loader: None,
+
+            last_export: None,
+            export_changes: ExportChanges::default(),
+            tracking_loader: None,
+            delta_loader: None,
 
             is_loading: false,
             loading_count: 0,
@@ -55,18 +167,125 @@ impl Default for CodeCollectorApp {
 }
 
 impl CodeCollectorApp {
+    // Restores the previous session and reopens its project
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self::default();
+        let Some(session) = cc.storage.and_then(|s| eframe::get_value::<Session>(s, eframe::APP_KEY)) else {
+            return app;
+        };
+
+        app.export_mode = session.export_mode.clone();
+        app.theme = session.theme.clone();
+        app.export_options = session.export_options.clone();
+        app.separate_options = session.separate_options.clone();
+        app.recent_projects = session.recent_projects.clone();
+        app.git_base_ref = session.git_base_ref.clone();
+
+        if let Some(path) = session.project_path.clone() {
+            if path.is_dir() {
+                app.project_path = Some(path.clone());
+                app.touch_recent_project(&path);
+                app.pending_session = Some(session);
+                app.start_scanning_thread(path);
+            } else {
+                app.status_text = format!("Last project not found: {}", path.display());
+            }
+        }
+        app
+    }
+
+    fn session_snapshot(&self) -> Session {
+        // While a restore is pending the tree is not there yet; keep what was loaded
+        if let Some(pending) = &self.pending_session {
+            return Session {
+                export_mode: self.export_mode.clone(),
+                theme: self.theme.clone(),
+                export_options: self.export_options.clone(),
+                separate_options: self.separate_options.clone(),
+                recent_projects: self.recent_projects.clone(),
+                git_base_ref: self.git_base_ref.clone(),
+                ..pending.clone()
+            };
+        }
+        Session {
+            project_path: self.project_path.clone(),
+            selections: self.current_selection_entries(),
+            search_query: self.search_query.clone(),
+            recent_files: self.recent_files.iter().map(|f| selection::normalize_rel_path(&f.rel_path)).collect(),
+            export_mode: self.export_mode.clone(),
+            theme: self.theme.clone(),
+            export_options: self.export_options.clone(),
+            separate_options: self.separate_options.clone(),
+            recent_projects: self.recent_projects.clone(),
+            git_base_ref: self.git_base_ref.clone(),
+        }
+    }
+
+    // Second half of a session restore, once the scan has produced the tree
+    fn apply_pending_session(&mut self) {
+        let Some(session) = self.pending_session.take() else { return; };
+        let Some(root) = &mut self.root_node else { return; };
+
+        selection::apply_entries(root, &session.selections, ApplyMode::Union);
+        let recents: Vec<FileNode> = session.recent_files.iter()
+            .filter_map(|rel_path| selection::find_file_by_rel_path(root, rel_path).cloned())
+            .collect();
+        for file in recents.into_iter().rev() {
+            self.add_to_recents(file);
+        }
+        self.search_query = session.search_query;
+    }
+
     
     pub fn open_folder_dialog(&mut self) {
         if let Some(path) = rfd::FileDialog::new().pick_folder() {
-            self.project_path = Some(path.clone());
-            self.start_scanning_thread(path);
+            self.open_project(path);
+        }
+    }
+
+    pub fn open_project(&mut self, path: PathBuf) {
+        self.project_path = Some(path.clone());
+        self.pending_session = None;
+        self.revision = None;
+        self.touch_recent_project(&path);
+        self.start_scanning_thread(path);
+    }
+
+    // --- Recent Projects ---
+
+    fn touch_recent_project(&mut self, path: &Path) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        match self.recent_projects.iter_mut().find(|p| p.path == path) {
+            Some(project) => project.last_opened = now,
+            None => self.recent_projects.push(RecentProject { path: path.to_path_buf(), pinned: false, last_opened: now }),
+        }
+
+        // Pinned projects never fall off the list
+        let mut unpinned: Vec<u64> = self.recent_projects.iter().filter(|p| !p.pinned).map(|p| p.last_opened).collect();
+        if unpinned.len() > MAX_RECENT_PROJECTS {
+            unpinned.sort_unstable_by(|a, b| b.cmp(a));
+            let cutoff = unpinned[MAX_RECENT_PROJECTS - 1];
+            self.recent_projects.retain(|p| p.pinned || p.last_opened >= cutoff);
+        }
+    }
+
+    pub fn toggle_recent_pin(&mut self, path: &Path) {
+        if let Some(project) = self.recent_projects.iter_mut().find(|p| p.path == path) {
+            project.pinned = !project.pinned;
         }
     }
 
+    // Pinned first, then most recently opened
+    pub fn sorted_recent_projects(&self) -> Vec<RecentProject> {
+        let mut projects = self.recent_projects.clone();
+        projects.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.last_opened.cmp(&a.last_opened)));
+        projects
+    }
+
     pub fn refresh_project(&mut self) {
         if let Some(path) = &self.project_path {
             // 1. Capture current selections before scanning
-            let mut set = HashSet::new();
+            let mut set = HashMap::new();
             if let Some(root) = &self.root_node {
                 selection::collect_selected_paths(root, &mut set);
             }
@@ -83,6 +302,9 @@ impl CodeCollectorApp {
         self.root_node = None;
         self.recent_files.clear();
         self.search_query.clear();
+        self.preview = None;
+        self.import_graph = None;
+        self.import_loader = None;
 
         let (tx, rx) = channel();
         self.loading_channel = Some(rx);
@@ -90,9 +312,20 @@ impl CodeCollectorApp {
         let cancel_flag = Arc::new(AtomicBool::new(false));
         self.cancel_flag = Some(cancel_flag.clone());
 
+        let revision = self.revision.clone();
         thread::spawn(move || {
             let root_path = path.clone();
-            if let Some(node) = read_dir_recursive_threaded(&path, &root_path, &tx, &cancel_flag) {
+            let node = match &revision {
+                Some(rev) => read_revision_tree(&path, rev, &tx, &cancel_flag),
+                None => read_dir_recursive_threaded(&path, &root_path, &tx, &cancel_flag).map(|mut node| {
+                    // Not a repository (or no git installed): no badges
+                    if let Ok(statuses) = git::status(&path) {
+                        git::apply_status(&mut node, &statuses);
+                    }
+                    node
+                }),
+            };
+            if let Some(node) = node {
                 if !cancel_flag.load(Ordering::Relaxed) {
                     let _ = tx.send(ScanMessage::Finished(node));
                 } else {
@@ -104,6 +337,20 @@ impl CodeCollectorApp {
         });
     }
 
+    // Reads every source file once, so it runs off the UI thread
+    fn start_import_indexing(&mut self, ctx: &egui::Context) {
+        let Some(root) = self.root_node.clone() else { return; };
+
+        let (tx, rx) = channel();
+        self.import_loader = Some(rx);
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let index = ProjectIndex::build(&root);
+            let _ = tx.send(ImportGraph::build(&index));
+            ctx.request_repaint();
+        });
+    }
+
     fn cancel_loading(&mut self) {
         if let Some(flag) = &self.cancel_flag {
             flag.store(true, Ordering::Relaxed);
@@ -112,11 +359,237 @@ impl CodeCollectorApp {
 
     pub fn select_all(&mut self, state: bool) {
         if let Some(root) = &mut self.root_node {
-            selection::set_dir_selection(root, state, &self.search_query);
+            let matches = self.search_matches.as_ref().map(|(_, m)| m);
+            selection::set_dir_selection(root, state, matches);
+            self.invalidate_token_estimate();
             self.update_status();
         }
     }
 
+    // Re-ranks the tree when the query changed; an empty query shows everything
+    fn refresh_search_matches(&mut self) {
+        let query = self.search_query.trim();
+        if query.is_empty() {
+            self.search_matches = None;
+            return;
+        }
+        if self.search_matches.as_ref().is_some_and(|(q, _)| q == query) {
+            return;
+        }
+        if let Some(root) = &self.root_node {
+            self.search_matches = Some((query.to_string(), search::match_tree(root, query)));
+        }
+    }
+
+    fn handle_tree_actions(&mut self, ctx: &egui::Context, actions: Vec<TreeAction>) {
+        for action in actions {
+            match action {
+                TreeAction::Selected(file) => self.add_to_recents(file),
+                TreeAction::OpenPreview(path) => self.open_preview(ctx, &path),
+                TreeAction::SelectDependencies(rel_path, depth) => self.select_with_dependencies(&rel_path, depth),
+                TreeAction::SelectionChanged => self.invalidate_token_estimate(),
+                TreeAction::SelectDependents(rel_path, depth) => self.select_dependents(&rel_path, depth),
+            }
+        }
+    }
+
+    pub fn select_with_dependencies(&mut self, rel_path: &str, depth: Option<usize>) {
+        let Some(root) = &mut self.root_node else { return; };
+        let Some(graph) = &self.import_graph else {
+            self.status_text = "Still indexing imports, try again in a moment.".to_string();
+            return;
+        };
+
+        let start = selection::normalize_rel_path(rel_path);
+        let deps = imports::collect_dependencies(graph, &start, depth);
+
+        let entries: Vec<SelectionEntry> = std::iter::once(start)
+            .chain(deps.iter().cloned())
+            .map(|rel_path| SelectionEntry { rel_path, ranges: Vec::new() })
+            .collect();
+        selection::apply_entries(root, &entries, ApplyMode::Union);
+        self.invalidate_token_estimate();
+        self.status_text = format!("Selected {} with {} local dependencies", rel_path, deps.len());
+    }
+
+    pub fn select_dependents(&mut self, rel_path: &str, depth: Option<usize>) {
+        let Some(root) = &mut self.root_node else { return; };
+        let Some(graph) = &self.import_graph else {
+            self.status_text = "Still indexing imports, try again in a moment.".to_string();
+            return;
+        };
+
+        let start = selection::normalize_rel_path(rel_path);
+        let users = imports::collect_dependents(graph, &start, depth);
+        let entries: Vec<SelectionEntry> = std::iter::once(start)
+            .chain(users.iter().cloned())
+            .map(|rel_path| SelectionEntry { rel_path, ranges: Vec::new() })
+            .collect();
+        selection::apply_entries(root, &entries, ApplyMode::Union);
+        self.invalidate_token_estimate();
+        self.status_text = format!("Selected {} with {} files that use it", rel_path, users.len());
+    }
+
+    pub fn missing_context(&self) -> Vec<(String, Vec<String>)> {
+        let (Some(root), Some(graph)) = (&self.root_node, &self.import_graph) else { return Vec::new(); };
+        let mut entries = Vec::new();
+        selection::collect_selected_entries(root, &mut entries);
+        let selected: Vec<String> = entries.into_iter().map(|e| e.rel_path).collect();
+        imports::missing_context(graph, &selected)
+    }
+
+    pub fn add_missing_imports(&mut self, missing: &[String]) {
+        let Some(root) = &mut self.root_node else { return; };
+        let entries: Vec<SelectionEntry> = missing.iter()
+            .map(|rel_path| SelectionEntry { rel_path: rel_path.clone(), ranges: Vec::new() })
+            .collect();
+        selection::apply_entries(root, &entries, ApplyMode::Union);
+        self.invalidate_token_estimate();
+        self.status_text = format!("Added {} imported files", entries.len());
+    }
+
+    // --- Writing model output back ---
+
+    // Writes files (None = delete) with a backup; returns false if nothing was written
+    pub fn write_changes(&mut self, writes: &[(String, Option<String>)]) -> bool {
+        let Some(project) = self.project_path.clone() else { return false; };
+        if writes.is_empty() {
+            self.status_text = "Nothing to apply.".to_string();
+            return false;
+        }
+        if self.revision.is_some() {
+            self.status_text = "Cannot write into a revision snapshot; go back to the working tree first.".to_string();
+            return false;
+        }
+        match backup::write_with_backup(&project, writes) {
+            Ok(snapshot) => {
+                self.refresh_project();
+                self.status_text = format!(
+                    "Applied {} files ({} new). Backup: {}",
+                    writes.len(),
+                    snapshot.created.len(),
+                    snapshot.dir.strip_prefix(&project).unwrap_or(&snapshot.dir).display()
+                );
+                self.last_snapshot = Some(snapshot);
+                true
+            }
+            Err(e) => {
+                self.status_text = format!("Error applying changes: {}", e);
+                false
+            }
+        }
+    }
+
+    pub fn apply_response_changes(&mut self, writes: &[(String, Option<String>)]) {
+        if self.write_changes(writes) {
+            self.apply_dialog = None;
+        }
+    }
+
+    pub fn apply_patch_changes(&mut self, writes: &[(String, Option<String>)]) {
+        if self.write_changes(writes) {
+            self.patch_dialog = None;
+        }
+    }
+
+    pub fn undo_last_apply(&mut self) {
+        let (Some(project), Some(snapshot)) = (self.project_path.clone(), self.last_snapshot.take()) else { return; };
+        match backup::restore(&project, &snapshot) {
+            Ok(()) => {
+                self.refresh_project();
+                self.status_text = format!("Undid changes to {} files", snapshot.modified.len() + snapshot.created.len());
+            }
+            Err(e) => {
+                self.status_text = format!("Undo failed: {}", e);
+                self.last_snapshot = Some(snapshot);
+            }
+        }
+    }
+
+    // --- Git ---
+
+    // Reloads the tree from a branch, tag or commit; selections carry over by path
+    pub fn open_at_revision(&mut self, rev: &str) {
+        let Some(project) = &self.project_path else { return; };
+        match git::resolve_commit(project, rev) {
+            Ok(_) => {
+                self.revision = Some(rev.trim().to_string());
+                self.refresh_project();
+            }
+            Err(e) => self.status_text = format!("Git: {}", e),
+        }
+    }
+
+    pub fn open_working_tree(&mut self) {
+        if self.revision.take().is_some() {
+            self.refresh_project();
+        }
+    }
+
+    pub fn select_working_tree_changes(&mut self) {
+        let Some(root) = &mut self.root_node else { return; };
+        let mut changed = Vec::new();
+        git::collect_changed(root, &mut changed);
+        let entries: Vec<SelectionEntry> = changed.into_iter()
+            .map(|rel_path| SelectionEntry { rel_path, ranges: Vec::new() })
+            .collect();
+        selection::apply_entries(root, &entries, ApplyMode::Union);
+        self.invalidate_token_estimate();
+        self.status_text = format!("Selected {} changed files", entries.len());
+    }
+
+    pub fn select_changed_since(&mut self) {
+        let (Some(root), Some(project)) = (&mut self.root_node, &self.project_path) else { return; };
+        let changed = match git::changed_since(project, &self.git_base_ref) {
+            Ok(changed) => changed,
+            Err(e) => {
+                self.status_text = format!("Git: {}", e);
+                return;
+            }
+        };
+
+        let entries: Vec<SelectionEntry> = changed.into_iter()
+            .map(|rel_path| SelectionEntry { rel_path, ranges: Vec::new() })
+            .collect();
+        let missing = selection::apply_entries(root, &entries, ApplyMode::Union);
+        self.invalidate_token_estimate();
+        self.status_text = format!("Selected {} files changed since {}", entries.len() - missing.len(), self.git_base_ref.trim());
+        if !missing.is_empty() {
+            self.status_text.push_str(&format!(" ({} not shown in the tree)", missing.len()));
+        }
+    }
+
+    pub fn open_preview(&mut self, ctx: &egui::Context, path: &Path) {
+        if self.preview.as_ref().is_some_and(|p| p.path == path) {
+            return;
+        }
+        let file = self.root_node.as_mut().and_then(|root| selection::find_file_mut(root, path));
+        if let Some(file) = file {
+            self.preview = Some(PreviewState::open(file, ctx));
+        }
+    }
+
+    // Opens the preview scrolled to a 1-based line, which stays marked
+    pub fn open_preview_at(&mut self, ctx: &egui::Context, rel_path: &str, line: usize) {
+        let path = self.root_node.as_ref().and_then(|root| selection::find_file_by_rel_path(root, rel_path)).map(|f| f.path.clone());
+        let Some(path) = path else { return; };
+        self.open_preview(ctx, &path);
+        if let Some(preview) = &mut self.preview {
+            preview.scroll_to = Some(line);
+            preview.marked_line = Some(line);
+        }
+    }
+
+    pub fn select_rel_paths(&mut self, rel_paths: &[String]) {
+        let Some(root) = &mut self.root_node else { return; };
+        let entries: Vec<SelectionEntry> = rel_paths.iter()
+            .map(|rel_path| SelectionEntry { rel_path: rel_path.clone(), ranges: Vec::new() })
+            .collect();
+        selection::apply_entries(root, &entries, ApplyMode::Union);
+        self.invalidate_token_estimate();
+        self.status_text = format!("Selected {} files", entries.len());
+    }
+
     pub fn add_to_recents(&mut self, file: FileNode) {
         self.recent_files.retain(|f| f.path != file.path);
         self.recent_files.push_front(file);
@@ -141,39 +614,105 @@ impl CodeCollectorApp {
     }
 
     fn save_single_file(&mut self) {
-        if let Some(root) = &self.root_node {
-            let mut content = String::new();
-            export::collect_content_string(root, &mut content);
-            
-            if content.is_empty() { 
+        if let Some(document) = self.assemble_export() {
+            if document.text.is_empty() { 
                 self.status_text = "No files selected!".to_string();
                 return; 
             }
 
             if let Some(path) = rfd::FileDialog::new().set_file_name(export::DEFAULT_OUTPUT_FILENAME).save_file() {
-                if let Err(e) = fs::write(&path, content) {
-                    self.status_text = format!("Error saving: {}", e);
-                } else {
-                    self.status_text = "Saved successfully!".to_string();
-                    let _ = open::that(&path);
-                }
+                self.write_single_file(&path, &document);
+            }
+        }
+    }
+
+    fn write_single_file(&mut self, path: &Path, document: &export::ExportDocument) {
+        if let Err(e) = fs::write(path, &document.text) {
+            self.status_text = format!("Error saving: {}", e);
+        } else {
+            self.status_text = "Saved successfully!".to_string();
+            if let Some(snapshot) = self.root_node.as_ref().map(|root| tracking::snapshot(root, document.hashes.clone())) {
+                self.record_export_snapshot(snapshot);
             }
+            self.record_history(ExportTarget::File(path.to_path_buf()), document.file_count(), export::estimate_tokens(&document.text));
+            let _ = open::that(path);
         }
     }
 
     fn save_separate_files(&mut self) {
-        if let Some(root) = &self.root_node {
+        if self.root_node.is_some() {
             if let Some(target_dir) = rfd::FileDialog::new().pick_folder() {
-                if let Err(e) = export::write_files_recursive(root, &target_dir) {
-                    self.status_text = format!("Error: {}", e);
+                // Cleaning is destructive, so it waits for the confirmation modal
+                if self.separate_options.clean_target && !is_dir_empty(&target_dir) {
+                    self.pending_clean_target = Some(target_dir);
+                } else {
+                    self.export_separate_files(&target_dir, false);
+                }
+            }
+        }
+    }
+
+    pub fn export_separate_files(&mut self, target_dir: &Path, clean_first: bool) {
+        let Some(root) = &self.root_node else { return; };
+
+        if clean_first {
+            // Never wipe a folder that contains the project we are exporting from
+            if let Some(project) = &self.project_path {
+                if project.starts_with(target_dir) {
+                    self.status_text = "Refusing to clean a folder that contains the project!".to_string();
+                    return;
+                }
+            }
+            if let Err(e) = export::clean_target_dir(target_dir) {
+                self.status_text = format!("Error cleaning target: {}", e);
+                return;
+            }
+        }
+
+        let mut report = export::ExportReport::default();
+        let result = export::write_files_recursive(root, target_dir, &self.separate_options, &mut report)
+            .and_then(|_| {
+                if self.separate_options.write_manifest {
+                    export::write_manifest(target_dir, &report)
                 } else {
-                    self.status_text = "Files exported successfully!".to_string();
-                    let _ = open::that(&target_dir);
+                    Ok(())
+                }
+            });
+
+        match result {
+            Err(e) => self.status_text = format!("Error: {}", e),
+            Ok(()) => {
+                self.status_text = format!(
+                    "Exported {} files ({} skipped, {} renamed)",
+                    report.written, report.skipped, report.renamed
+                );
+                if report.collisions > 0 {
+                    self.status_text.push_str(&format!(" — {} files had the same target name and were renamed", report.collisions));
                 }
+                let snapshot = tracking::snapshot(root, report.hashes);
+                self.record_export_snapshot(snapshot);
+                // Files are written as they are; the estimate of the selection is the best number at hand
+                self.record_history(ExportTarget::Folder(target_dir.to_path_buf()), report.written, self.token_estimate.unwrap_or(0));
+                let _ = open::that(target_dir);
             }
         }
     }
 
+    // The single code path behind copy, save and the export preview
+    pub fn assemble_export(&self) -> Option<export::ExportDocument> {
+        self.root_node.as_ref().map(|root| export::build_export(root, &self.export_options))
+    }
+
+    pub fn open_export_preview(&mut self) {
+        if let Some(root) = &self.root_node {
+            if !selection::validate_selections(root) {
+                self.show_missing_files_alert = true;
+                return;
+            }
+        }
+        self.export_preview = self.assemble_export().map(ExportPreview::new);
+    }
+
     pub fn copy_to_clipboard(&mut self) {
         if let Some(root) = &self.root_node {
             // Validation
@@ -182,20 +721,321 @@ impl CodeCollectorApp {
                 return;
             }
 
-            let mut content = String::new();
-            export::collect_content_string(root, &mut content);
-            if !content.is_empty() {
-                if let Ok(mut clipboard) = arboard::Clipboard::new() {
-                    let _ = clipboard.set_text(content);
-                    self.status_text = "Copied to clipboard!".to_string();
+            let Some(document) = self.assemble_export() else { return; };
+            if !document.text.is_empty() {
+                let snapshot = tracking::snapshot(root, document.hashes.clone());
+                self.copy_document(&document, snapshot);
+            }
+        }
+    }
+
+    // Every copy goes through here, so each one moves the change-tracking baseline
+    // to `snapshot` and lands in the export history
+    pub fn copy_document(&mut self, document: &export::ExportDocument, snapshot: ExportSnapshot) -> bool {
+        let copied = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(document.text.clone()));
+        if let Err(e) = copied {
+            self.status_text = format!("Cannot copy to the clipboard: {}", e);
+            return false;
+        }
+        self.status_text = "Copied to clipboard!".to_string();
+        self.record_export_snapshot(snapshot);
+        self.record_history(ExportTarget::Clipboard, document.file_count(), export::estimate_tokens(&document.text));
+        true
+    }
+
+    // Called whenever the selection or the export options change
+    pub fn invalidate_token_estimate(&mut self) {
+        self.token_estimate_dirty = true;
+    }
+
+    // Starts a new estimate when one is due; a newer request drops the older receiver
+    fn poll_token_estimate(&mut self, ctx: &egui::Context) {
+        if let Some(rx) = &self.token_loader {
+            if let Ok(estimate) = rx.try_recv() {
+                self.token_estimate = Some(estimate.tokens);
+                self.truncated_files = estimate.truncated;
+                self.token_loader = None;
+            }
+        }
+        if !self.token_estimate_dirty {
+            return;
+        }
+        let Some(root) = &self.root_node else { return; };
+        self.token_estimate_dirty = false;
+        self.token_estimate = None;
+
+        let mut files = Vec::new();
+        selection::collect_selected_files(root, &mut files);
+        let options = self.export_options.clone();
+        let (tx, rx) = channel();
+        self.token_loader = Some(rx);
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(export::estimate_selection(&files, &options));
+            ctx.request_repaint();
+        });
+    }
+
+    // --- Change Tracking ---
+
+    // Hashing every exported file takes a while on big projects, so the compare runs in the background.
+    // A project opened at a revision has nothing on disk to compare.
+    fn load_export_tracking(&mut self, ctx: &egui::Context) {
+        self.export_changes = ExportChanges::default();
+        self.tracking_loader = None;
+        self.last_export = None;
+        if self.revision.is_some() {
+            return;
+        }
+        let snapshot: Option<ExportSnapshot> = self.project_path.as_deref()
+            .map(|project| project_store::load(project, tracking::LAST_EXPORT_FILE))
+            .filter(|s: &ExportSnapshot| !s.files.is_empty());
+        let (Some(root), Some(snapshot)) = (self.root_node.clone(), snapshot) else { return; };
+
+        let (tx, rx) = channel();
+        self.tracking_loader = Some(rx);
+        self.last_export = Some(snapshot.clone());
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(tracking::compare(&root, &snapshot));
+            ctx.request_repaint();
+        });
+    }
+
+    fn poll_export_tracking(&mut self) {
+        if let Some(rx) = &self.tracking_loader {
+            if let Ok(changes) = rx.try_recv() {
+                self.export_changes = changes;
+                self.tracking_loader = None;
+            }
+        }
+        if let Some(rx) = &self.delta_loader {
+            if let Ok(delta) = rx.try_recv() {
+                self.delta_loader = None;
+                if delta.document.text.is_empty() {
+                    self.status_text = "Nothing changed since the last export".to_string();
+                } else if self.copy_document(&delta.document, delta.snapshot) {
+                    self.status_text = format!("Copied delta ({} files)", delta.document.file_count());
+                }
+            }
+        }
+    }
+
+    fn save_export_snapshot(&mut self, snapshot: ExportSnapshot) {
+        if let Some(project) = &self.project_path {
+            if let Err(e) = project_store::save(project, tracking::LAST_EXPORT_FILE, &snapshot) {
+                self.status_text = format!("Error saving export snapshot: {}", e);
+            }
+        }
+        self.last_export = Some(snapshot);
+        self.export_changes = ExportChanges::default();
+        self.tracking_loader = None;
+    }
+
+    // Called after every successful export; a revision snapshot is not what the next export compares against
+    fn record_export_snapshot(&mut self, snapshot: ExportSnapshot) {
+        if self.revision.is_none() {
+            self.save_export_snapshot(snapshot);
+        }
+    }
+
+    pub fn select_changed_since_export(&mut self) {
+        let entries: Vec<SelectionEntry> = self.export_changes.changed().into_iter()
+            .map(|rel_path| SelectionEntry { rel_path, ranges: Vec::new() })
+            .collect();
+        if let Some(root) = &mut self.root_node {
+            selection::apply_entries(root, &entries, ApplyMode::Union);
+            self.invalidate_token_estimate();
+            self.status_text = format!("Selected {} files changed since the last export", entries.len());
+        }
+    }
+
+    // Copies only what changed since the last export, then moves the baseline forward.
+    // Compares again first, in the background, so edits since the last check are included.
+    pub fn copy_export_delta(&mut self, ctx: &egui::Context) {
+        let (Some(root), Some(snapshot)) = (self.root_node.clone(), self.last_export.clone()) else { return; };
+        let options = self.export_options.clone();
+        let (tx, rx) = channel();
+        self.delta_loader = Some(rx);
+        self.status_text = "Collecting changes since the last export…".to_string();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(tracking::export_delta(&root, &snapshot, &options));
+            ctx.request_repaint();
+        });
+    }
+
+    // Reads a file exported by this tool (possibly from another machine) to restore its selection
+    pub fn open_import_selection(&mut self) {
+        let Some(root) = &self.root_node else { return; };
+        let Some(path) = rfd::FileDialog::new().add_filter("Exports", &["txt", "md", "xml"]).add_filter("All files", &["*"]).pick_file() else { return; };
+        match ImportSelectionDialog::load(root, &path) {
+            Ok(dialog) => self.import_dialog = Some(dialog),
+            Err(e) => self.status_text = e,
+        }
+    }
+
+    // --- Export History ---
+
+    fn load_history(&mut self) {
+        self.history = self.project_path.as_deref()
+            .map(|project| project_store::load(project, HISTORY_FILE))
+            .unwrap_or_default();
+    }
+
+    fn record_history(&mut self, target: ExportTarget, file_count: usize, tokens: usize) {
+        let record = ExportRecord {
+            exported_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            target,
+            file_count,
+            tokens,
+            entries: self.current_selection_entries(),
+            options: Some(self.export_options.clone()),
+            separate_options: Some(self.separate_options.clone()),
+        };
+        self.history.insert(0, record);
+        self.history.truncate(MAX_HISTORY);
+        if let Some(project) = &self.project_path {
+            if let Err(e) = project_store::save(project, HISTORY_FILE, &self.history) {
+                self.status_text = format!("Error saving export history: {}", e);
+            }
+        }
+    }
+
+    // Restores the entry's selection and exports it again, to the same place, with today's file contents.
+    // The history panel asks first (pending_rerun), since both the selection and the old output are replaced.
+    pub fn rerun_export(&mut self, idx: usize) {
+        let Some(record) = self.history.get(idx).cloned() else { return; };
+        let Some(root) = &mut self.root_node else { return; };
+        let missing = selection::apply_entries(root, &record.entries, ApplyMode::Replace);
+        let valid = selection::validate_selections(root);
+        self.invalidate_token_estimate();
+        if !valid {
+            self.show_missing_files_alert = true;
+            return;
+        }
+        // The entry's own settings, for this run only
+        let options = record.options.clone().map(|o| std::mem::replace(&mut self.export_options, o));
+        let separate_options = record.separate_options.clone().map(|o| std::mem::replace(&mut self.separate_options, o));
+        match &record.target {
+            ExportTarget::Clipboard => self.copy_to_clipboard(),
+            ExportTarget::File(path) => {
+                if let Some(document) = self.assemble_export() {
+                    self.write_single_file(path, &document);
                 }
             }
+            ExportTarget::Folder(path) => self.export_separate_files(path, false),
+        }
+        if let Some(options) = options {
+            self.export_options = options;
+        }
+        if let Some(separate_options) = separate_options {
+            self.separate_options = separate_options;
+        }
+        if !missing.is_empty() {
+            self.status_text.push_str(&format!(" — {} files of that export no longer exist", missing.len()));
+        }
+    }
+
+    pub fn history_to_profile(&mut self, idx: usize) {
+        let Some(record) = self.history.get(idx).cloned() else { return; };
+        let name = std::mem::take(&mut self.history_profile_name).trim().to_string();
+        if name.is_empty() { return; }
+
+        match self.profiles.iter().position(|p| p.name == name) {
+            Some(existing) => self.profiles[existing].entries = record.entries,
+            None => self.profiles.push(SelectionProfile { name: name.clone(), entries: record.entries }),
+        }
+        self.active_profile = self.profiles.iter().position(|p| p.name == name);
+        self.save_profiles();
+        self.status_text = format!("Saved profile \"{}\"", name);
+    }
+
+    // --- Selection Profiles ---
+
+    fn load_profiles(&mut self) {
+        self.profiles = self.project_path.as_deref()
+            .map(|project| project_store::load(project, PROFILES_FILE))
+            .unwrap_or_default();
+        self.active_profile = None;
+    }
+
+    fn save_profiles(&mut self) {
+        if let Some(project) = &self.project_path {
+            if let Err(e) = project_store::save(project, PROFILES_FILE, &self.profiles) {
+                self.status_text = format!("Error saving profiles: {}", e);
+            }
+        }
+    }
+
+    fn current_selection_entries(&self) -> Vec<SelectionEntry> {
+        let mut entries = Vec::new();
+        if let Some(root) = &self.root_node {
+            selection::collect_selected_entries(root, &mut entries);
+        }
+        entries
+    }
+
+    pub fn save_profile_as(&mut self, name: &str) {
+        let name = name.trim().to_string();
+        if name.is_empty() { return; }
+
+        let entries = self.current_selection_entries();
+        match self.profiles.iter().position(|p| p.name == name) {
+            Some(idx) => {
+                self.profiles[idx].entries = entries;
+                self.active_profile = Some(idx);
+            }
+            None => {
+                self.profiles.push(SelectionProfile { name: name.clone(), entries });
+                self.active_profile = Some(self.profiles.len() - 1);
+            }
+        }
+        self.save_profiles();
+        self.status_text = format!("Saved profile \"{}\"", name);
+    }
+
+    pub fn overwrite_active_profile(&mut self) {
+        if let Some(name) = self.active_profile.and_then(|idx| self.profiles.get(idx)).map(|p| p.name.clone()) {
+            self.save_profile_as(&name);
+        }
+    }
+
+    pub fn delete_active_profile(&mut self) {
+        if let Some(idx) = self.active_profile.take() {
+            if idx < self.profiles.len() {
+                self.profiles.remove(idx);
+                self.save_profiles();
+            }
+        }
+    }
+
+    pub fn apply_active_profile(&mut self, mode: ApplyMode) {
+        let Some(profile) = self.active_profile.and_then(|idx| self.profiles.get(idx)).cloned() else { return; };
+        let Some(root) = &mut self.root_node else { return; };
+
+        let missing = selection::apply_entries(root, &profile.entries, mode);
+        self.invalidate_token_estimate();
+        self.status_text = format!("Applied profile \"{}\"", profile.name);
+        if !missing.is_empty() {
+            self.profile_missing = Some((profile.name, missing));
+        }
+    }
+
+    // Drops entries whose files are gone from the named profile
+    pub fn prune_profile(&mut self, name: &str, missing: &[String]) {
+        if let Some(profile) = self.profiles.iter_mut().find(|p| p.name == name) {
+            profile.entries.retain(|e| !missing.contains(&e.rel_path));
+            self.save_profiles();
         }
     }
 
     fn update_status(&mut self) {
         if let Some(project) = &self.project_path {
              self.status_text = format!("Project: {}", project.file_name().unwrap_or_default().to_string_lossy());
+             if let Some(rev) = &self.revision {
+                 self.status_text.push_str(&format!(" @ {} (read-only snapshot)", rev));
+             }
         }
     }
 
@@ -228,6 +1068,10 @@ impl CodeCollectorApp {
 }
 
 impl eframe::App for CodeCollectorApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.session_snapshot());
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // 1. Apply Theme
         self.configure_theme(ctx); 
@@ -257,6 +1101,7 @@ impl eframe::App for CodeCollectorApp {
                         ScanMessage::Cancelled => {
                             self.is_loading = false;
                             self.preserved_selections = None;
+                            self.pending_session = None;
                             self.status_text = "Scanning cancelled.".to_string();
                             self.root_node = None; 
                         }
@@ -267,7 +1112,26 @@ impl eframe::App for CodeCollectorApp {
 
         if scan_completed {
             self.update_status();
+            self.load_profiles();
+            self.load_history();
+            self.apply_pending_session();
+            self.start_import_indexing(ctx);
+            self.load_export_tracking(ctx);
+        }
+        if let Some(rx) = &self.import_loader {
+            if let Ok(graph) = rx.try_recv() {
+                self.import_graph = Some(graph);
+                self.import_loader = None;
+            }
+        }
+
+        if scan_completed {
+            self.search_matches = None;
+            self.invalidate_token_estimate();
         }
+        self.refresh_search_matches();
+        self.poll_token_estimate(ctx);
+        self.poll_export_tracking();
 
         // 3. Render Panels
         panels::show_top_panel(ctx, self);
@@ -282,16 +1146,43 @@ impl eframe::App for CodeCollectorApp {
                    ui.add_space(50.0);
                 });
             } else if self.root_node.is_some() {
+                // Preview pane splits the central area
+                if self.preview.is_some() {
+                    egui::SidePanel::right("preview_panel")
+                        .resizable(true)
+                        .default_width(ui.available_width() / 2.0)
+                        .show_inside(ui, |ui| preview::show_preview_panel(ui, self));
+                }
+
+                let mut actions = Vec::new();
                 egui::ScrollArea::vertical().show(ui, |ui| {
                     if let Some(root) = &mut self.root_node {
-                        if let Some(just_selected) = tree::render_tree_main(ui, root, true, &self.search_query) {
-                            self.add_to_recents(just_selected);
+                        let tree_ctx = tree::TreeContext {
+                            matches: self.search_matches.as_ref().map(|(_, m)| m),
+                            truncated: &self.truncated_files,
+                            previewed: self.preview.as_ref().map(|p| p.path.as_path()),
+                            changed_since_export: self.last_export.as_ref().map(|_| &self.export_changes),
+                        };
+                        if self.ranked_view && tree_ctx.matches.is_some() {
+                            tree::render_ranked_list(ui, root, &tree_ctx, &mut actions);
+                        } else {
+                            tree::render_tree_main(ui, root, true, &tree_ctx, &mut actions);
                         }
                     }
                 });
+                self.handle_tree_actions(ctx, actions);
             } else {
-                ui.centered_and_justified(|ui| {
-                    ui.label("Open a project to begin.");
+                ui.vertical_centered(|ui| {
+                    ui.add_space(60.0);
+                    ui.label(egui::RichText::new("Open a project to begin.").size(16.0));
+                    ui.add_space(20.0);
+                    if !self.recent_projects.is_empty() {
+                        ui.label(egui::RichText::new("Recent Projects").strong());
+                        ui.add_space(5.0);
+                        ui.allocate_ui(egui::vec2(400.0, 0.0), |ui| {
+                            recents::show_recent_projects_list(ui, self);
+                        });
+                    }
                 });
             }
         });
@@ -349,5 +1240,115 @@ impl eframe::App for CodeCollectorApp {
                     });
                 });
         }
+
+        // C. Export Preview, Pattern Selection, Import Selection, Apply Response & Apply Patch
+        export_preview::show_export_preview_window(ctx, self);
+        pattern_dialog::show_pattern_dialog(ctx, self);
+        import_selection::show_import_selection_window(ctx, self);
+        content_search::show_content_search_window(ctx, self);
+        apply_response::show_apply_response_window(ctx, self);
+        patch_dialog::show_patch_dialog(ctx, self);
+
+        // D. Profile Entries Missing
+        if let Some((name, missing)) = self.profile_missing.clone() {
+            egui::Window::new("⚠️ Missing Profile Files")
+                .collapsible(false)
+                .resizable(true)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.set_min_width(300.0);
+                    ui.label(format!("{} file(s) of \"{}\" no longer exist:", missing.len(), name));
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        for path in &missing {
+                            ui.label(egui::RichText::new(path).monospace().weak());
+                        }
+                    });
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("Remove From Profile").clicked() {
+                            self.prune_profile(&name, &missing);
+                            self.profile_missing = None;
+                        }
+                        if ui.button("Keep").clicked() {
+                            self.profile_missing = None;
+                        }
+                    });
+                });
+        }
+
+        // E. Re-run Confirmation: it replaces the selection and may overwrite the old output
+        if let Some((idx, record)) = self.pending_rerun.and_then(|idx| Some((idx, self.history.get(idx)?.clone()))) {
+            let selected = self.current_selection_entries().len();
+            egui::Window::new("↻ Re-run Export")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.set_min_width(300.0);
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(10.0);
+                        ui.label(format!("Your current selection ({} files) will be replaced by the {} files of that export.", selected, record.entries.len()));
+                        match &record.target {
+                            ExportTarget::File(path) if path.exists() => {
+                                ui.colored_label(egui::Color32::RED, "This file will be overwritten:");
+                                ui.label(egui::RichText::new(path.to_string_lossy()).monospace());
+                            }
+                            ExportTarget::Folder(path) if !is_dir_empty(path) => {
+                                ui.colored_label(egui::Color32::RED, "Files already in this folder may be overwritten:");
+                                ui.label(egui::RichText::new(path.to_string_lossy()).monospace());
+                            }
+                            _ => {}
+                        }
+                        ui.add_space(15.0);
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Re-run").clicked() {
+                                self.pending_rerun = None;
+                                self.rerun_export(idx);
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.pending_rerun = None;
+                            }
+                        });
+                        ui.add_space(10.0);
+                    });
+                });
+        }
+
+        // F. Clean Target Confirmation
+        if let Some(target) = self.pending_clean_target.clone() {
+            egui::Window::new("🧹 Clean Target Folder")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.set_min_width(300.0);
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(10.0);
+                        ui.colored_label(egui::Color32::RED, "Everything inside this folder will be deleted:");
+                        ui.label(egui::RichText::new(target.to_string_lossy()).monospace());
+                        ui.add_space(15.0);
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Delete & Export").clicked() {
+                                self.pending_clean_target = None;
+                                self.export_separate_files(&target, true);
+                            }
+                            if ui.button("Export Without Cleaning").clicked() {
+                                self.pending_clean_target = None;
+                                self.export_separate_files(&target, false);
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.pending_clean_target = None;
+                            }
+                        });
+                        ui.add_space(10.0);
+                    });
+                });
+        }
     }
-}
\ No newline at end of file
+}
+
+fn is_dir_empty(path: &Path) -> bool {
+    fs::read_dir(path).map(|mut entries| entries.next().is_none()).unwrap_or(true)
+}
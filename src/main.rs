@@ -14,5 +14,5 @@ fn main() -> Result<(), eframe::Error> {
         viewport: egui::ViewportBuilder::default().with_inner_size([900.0, 800.0]),
         ..Default::default()
     };
-    eframe::run_native("Code Collector", options, Box::new(|_cc| Ok(Box::new(CodeCollectorApp::default()))))
+    eframe::run_native("Code Collector", options, Box::new(|cc| Ok(Box::new(CodeCollectorApp::new(cc)))))
 }
\ No newline at end of file
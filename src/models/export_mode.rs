@@ -1,5 +1,7 @@
-#[derive(Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum ExportMode {
     OneFile,
     SeparateFiles,
-}
\ No newline at end of file
+}
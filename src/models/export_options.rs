@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ConflictPolicy {
+    Overwrite,
+    Skip,
+    Rename,
+}
+
+impl ConflictPolicy {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Overwrite => "Overwrite",
+            ConflictPolicy::Skip => "Skip",
+            ConflictPolicy::Rename => "Rename",
+        }
+    }
+}
+
+// Settings for the "Separate Files" export mode
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SeparateFilesOptions {
+    pub conflict_policy: ConflictPolicy,
+    pub clean_target: bool,       // Empty the target folder first (asks for confirmation)
+    pub flatten: bool,            // src/ui/tree.rs -> src__ui__tree.rs
+    pub preserve_metadata: bool,  // Keep modification time and permissions of the source
+    pub write_manifest: bool,
+}
+
+impl Default for SeparateFilesOptions {
+    fn default() -> Self {
+        Self {
+            conflict_policy: ConflictPolicy::Overwrite,
+            clean_target: false,
+            flatten: false,
+            preserve_metadata: true,
+            write_manifest: true,
+        }
+    }
+}
+
+// Text transforms applied to each file before it is written into a text export
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransformOptions {
+    pub strip_comments: bool,
+    pub strip_license: bool,
+    pub collapse_blank_lines: bool,
+    pub trim_trailing_whitespace: bool,
+}
+
+impl TransformOptions {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum LineNumberStyle {
+    #[default]
+    Pipe,  // "  12 | code"
+    Colon, // "  12: code"
+    Tab,   // "12\tcode"
+}
+
+impl LineNumberStyle {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LineNumberStyle::Pipe => "12 | code",
+            LineNumberStyle::Colon => "12: code",
+            LineNumberStyle::Tab => "12⇥code",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LineNumberOptions {
+    pub enabled: bool,
+    pub width: usize, // Minimum digits; 0 = fit the longest number in the file
+    pub style: LineNumberStyle,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum TruncationLimit {
+    #[default]
+    Lines,
+    Tokens,
+}
+
+// Per-file size cap: oversized files keep their head and tail around an omission marker
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TruncationOptions {
+    pub enabled: bool,
+    pub limit: TruncationLimit,
+    pub max: usize,
+    pub head_lines: usize,
+    pub tail_lines: usize,
+}
+
+impl Default for TruncationOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            limit: TruncationLimit::Lines,
+            max: 1000,
+            head_lines: 200,
+            tail_lines: 50,
+        }
+    }
+}
+
+// Export git changes instead of whole files
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffOptions {
+    pub enabled: bool,
+    pub base_ref: String,
+    pub target_ref: String, // Empty = the working tree
+    pub context_lines: usize,
+    pub include_full_file: bool, // Append the new version after the diff
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            base_ref: "main".to_string(),
+            target_ref: String::new(),
+            context_lines: 3,
+            include_full_file: false,
+        }
+    }
+}
+
+impl DiffOptions {
+    pub fn target_label(&self) -> &str {
+        if self.target_ref.trim().is_empty() { "working tree" } else { self.target_ref.trim() }
+    }
+}
+
+// Everything that shapes the text of a "Single File" / clipboard export
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub transform: TransformOptions,
+    pub line_numbers: LineNumberOptions,
+    pub skeleton: bool, // Signatures only, for every supported file
+    pub truncation: TruncationOptions,
+    pub diff: DiffOptions,
+}
+
+impl ExportOptions {
+    // True when files are exported exactly as they are on disk
+    pub fn is_raw(&self) -> bool {
+        self.transform.is_identity() && !self.line_numbers.enabled && !self.skeleton && !self.truncation.enabled && !self.diff.enabled
+    }
+}
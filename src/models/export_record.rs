@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use super::export_options::{ExportOptions, SeparateFilesOptions};
+use super::profile::SelectionEntry;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ExportTarget {
+    Clipboard,
+    File(PathBuf),
+    Folder(PathBuf), // Separate files
+}
+
+impl ExportTarget {
+    pub fn label(&self) -> String {
+        match self {
+            ExportTarget::Clipboard => "Clipboard".to_string(),
+            ExportTarget::File(path) | ExportTarget::Folder(path) => path.to_string_lossy().to_string(),
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            ExportTarget::Clipboard => "📋",
+            ExportTarget::File(_) => "💾",
+            ExportTarget::Folder(_) => "📁",
+        }
+    }
+}
+
+// One past export, newest first in the per-project history
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportRecord {
+    pub exported_at: u64, // Unix seconds
+    pub target: ExportTarget,
+    pub file_count: usize,
+    pub tokens: usize,
+    pub entries: Vec<SelectionEntry>,
+    // Settings the export ran with; None in records written before they were stored
+    #[serde(default)]
+    pub options: Option<ExportOptions>,
+    #[serde(default)]
+    pub separate_options: Option<SeparateFilesOptions>,
+}
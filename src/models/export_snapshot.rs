@@ -0,0 +1,11 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+
+// Content hashes of the files in the last export (stored per project)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSnapshot {
+    pub exported_at: u64,                // Unix seconds
+    pub files: BTreeMap<String, String>, // rel_path -> sha256 of the file as it was on disk
+    pub project_files: BTreeSet<String>, // Every file in the project then; empty in older snapshots
+}
@@ -1,4 +1,10 @@
 use std::path::PathBuf;
+use super::git_blob::GitBlob;
+use super::git_status::GitStatus;
+use super::line_range::LineRange;
+
+// Average line length used while a file's lines haven't been counted
+const ESTIMATED_LINE_BYTES: u64 = 30;
 
 #[derive(Clone)]
 pub struct FileNode {
@@ -8,4 +14,34 @@ pub struct FileNode {
     pub extension: String,
     pub selected: bool,
     pub size_bytes: u64,
-}
\ No newline at end of file
+    pub line_count: Option<usize>, // None until the file has been read
+    pub skeleton: bool, // Export signatures only, regardless of the export-wide setting
+    pub line_ranges: Vec<LineRange>, // Empty = the whole file
+    pub git_status: Option<GitStatus>, // None = unchanged or not a git repository
+    pub blob: Option<GitBlob>, // Set when the project is opened at a git revision
+}
+
+impl FileNode {
+    // Deselecting drops the line ranges, so checking the file again selects all of it
+    pub fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+        if !selected {
+            self.line_ranges.clear();
+        }
+    }
+
+    // Exact line count once known, estimated from the size until then
+    pub fn lines(&self) -> usize {
+        self.line_count.unwrap_or_else(|| self.size_bytes.div_ceil(ESTIMATED_LINE_BYTES) as usize)
+    }
+
+    // Size of the part that gets exported (proportional when only some lines are selected)
+    pub fn selected_bytes(&self) -> u64 {
+        let total = self.lines();
+        if self.line_ranges.is_empty() || total == 0 {
+            return self.size_bytes;
+        }
+        let lines: usize = self.line_ranges.iter().map(|r| r.len()).sum();
+        self.size_bytes * lines.min(total) as u64 / total as u64
+    }
+}
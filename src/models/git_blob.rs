@@ -0,0 +1,8 @@
+use std::path::PathBuf;
+
+// A file inside a git revision rather than on disk
+#[derive(Clone, Debug)]
+pub struct GitBlob {
+    pub repo_dir: PathBuf, // Any folder inside the repository (the project folder)
+    pub id: String,
+}
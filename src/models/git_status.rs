@@ -0,0 +1,34 @@
+// Working tree state of a file, from `git status`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GitStatus {
+    Modified,   // Unstaged changes
+    Staged,     // Changes only in the index
+    Added,
+    Renamed,
+    Untracked,
+    Conflicted,
+}
+
+impl GitStatus {
+    pub fn badge(&self) -> &'static str {
+        match self {
+            GitStatus::Modified => "M",
+            GitStatus::Staged => "S",
+            GitStatus::Added => "A",
+            GitStatus::Renamed => "R",
+            GitStatus::Untracked => "U",
+            GitStatus::Conflicted => "!",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GitStatus::Modified => "Modified",
+            GitStatus::Staged => "Staged",
+            GitStatus::Added => "Added",
+            GitStatus::Renamed => "Renamed",
+            GitStatus::Untracked => "Untracked",
+            GitStatus::Conflicted => "Conflicted",
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+// An inclusive, 1-based range of lines within a file
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LineRange {
+    pub fn new(a: usize, b: usize) -> Self {
+        Self { start: a.min(b), end: a.max(b) }
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        line >= self.start && line <= self.end
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start + 1
+    }
+}
+
+// Adds a range, keeping the list sorted and merging overlapping or adjacent ranges
+pub fn add_range(ranges: &mut Vec<LineRange>, range: LineRange) {
+    ranges.push(range);
+    ranges.sort_by_key(|r| r.start);
+
+    let mut merged: Vec<LineRange> = Vec::with_capacity(ranges.len());
+    for r in ranges.drain(..) {
+        match merged.last_mut() {
+            Some(last) if r.start <= last.end + 1 => last.end = last.end.max(r.end),
+            _ => merged.push(r),
+        }
+    }
+    *ranges = merged;
+}
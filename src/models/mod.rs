@@ -2,4 +2,13 @@ pub mod file_node;
 pub mod dir_node;
 pub mod export_mode;
 
-pub mod theme;
\ No newline at end of file
+pub mod theme;
+pub mod export_options;
+pub mod line_range;
+pub mod profile;
+pub mod session;
+pub mod recent_project;
+pub mod git_status;
+pub mod git_blob;
+pub mod export_snapshot;
+pub mod export_record;
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use super::line_range::LineRange;
+
+// One file of a saved selection. `rel_path` always uses '/' so profiles work across platforms.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SelectionEntry {
+    pub rel_path: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ranges: Vec<LineRange>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SelectionProfile {
+    pub name: String,
+    pub entries: Vec<SelectionEntry>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ApplyMode {
+    Replace, // Clear the current selection first
+    Union,   // Add to the current selection
+}
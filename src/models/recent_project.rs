@@ -0,0 +1,19 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecentProject {
+    pub path: PathBuf,
+    pub pinned: bool,
+    pub last_opened: u64, // Unix seconds
+}
+
+impl RecentProject {
+    pub fn name(&self) -> String {
+        self.path.file_name().unwrap_or(self.path.as_os_str()).to_string_lossy().to_string()
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.is_dir()
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use super::export_mode::ExportMode;
+use super::export_options::{ExportOptions, SeparateFilesOptions};
+use super::profile::SelectionEntry;
+use super::recent_project::RecentProject;
+use super::theme::ThemePreference;
+
+// Everything restored on the next launch (stored through eframe's storage)
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub project_path: Option<PathBuf>,
+    pub selections: Vec<SelectionEntry>,
+    pub search_query: String,
+    pub recent_files: Vec<String>, // rel paths, most recent first
+    pub export_mode: ExportMode,
+    pub theme: ThemePreference,
+    pub export_options: ExportOptions,
+    pub separate_options: SeparateFilesOptions,
+    pub recent_projects: Vec<RecentProject>,
+    pub git_base_ref: String,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            project_path: None,
+            selections: Vec::new(),
+            search_query: String::new(),
+            recent_files: Vec::new(),
+            export_mode: ExportMode::OneFile,
+            theme: ThemePreference::System,
+            export_options: ExportOptions::default(),
+            separate_options: SeparateFilesOptions::default(),
+            recent_projects: Vec::new(),
+            git_base_ref: "main".to_string(),
+        }
+    }
+}
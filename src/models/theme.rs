@@ -1,12 +1,9 @@
-#[derive(Clone, PartialEq, Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub enum ThemePreference {
     Dark,
     Light,
+    #[default]
     System,
 }
-
-impl Default for ThemePreference {
-    fn default() -> Self {
-        Self::System
-    }
-}
\ No newline at end of file
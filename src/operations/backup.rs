@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use super::project_store;
+
+const BACKUPS_DIR: &str = "backups";
+const SNAPSHOT_FILE: &str = "snapshot.json";
+
+// What one write touched; originals of `modified` (and deleted files) are copied next to snapshot.json
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub dir: PathBuf,
+    pub modified: Vec<String>,
+    pub created: Vec<String>,
+    #[serde(default)]
+    pub written: BTreeMap<String, Option<String>>, // Hash of what was written (None = deleted)
+}
+
+// Writes (rel_path, content) pairs into the project after copying every file it
+// overwrites to .code_collector/backups/<timestamp>/. A None content deletes the file.
+pub fn write_with_backup(project: &Path, changes: &[(String, Option<String>)]) -> io::Result<Snapshot> {
+    project_store::create_data_dir(project)?;
+    let mut snapshot = Snapshot { dir: new_backup_dir(project), ..Default::default() };
+    fs::create_dir_all(&snapshot.dir)?;
+
+    // Back up everything first, so a failed write never leaves a file without its original
+    for (rel_path, _) in changes {
+        let target = project.join(rel_path);
+        if target.exists() {
+            let backup = snapshot.dir.join(rel_path);
+            if let Some(parent) = backup.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(&target, &backup)?;
+            snapshot.modified.push(rel_path.clone());
+        } else {
+            snapshot.created.push(rel_path.clone());
+        }
+    }
+    snapshot.written = changes.iter()
+        .map(|(rel_path, content)| (rel_path.clone(), content.as_ref().map(|c| hash(c.as_bytes()))))
+        .collect();
+    let text = serde_json::to_string_pretty(&snapshot).map_err(io::Error::other)?;
+    fs::write(snapshot.dir.join(SNAPSHOT_FILE), text)?;
+
+    for (rel_path, content) in changes {
+        let target = project.join(rel_path);
+        match content {
+            Some(content) => {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, content)?;
+            }
+            None => {
+                if target.exists() {
+                    fs::remove_file(&target)?;
+                }
+            }
+        }
+    }
+    Ok(snapshot)
+}
+
+// Puts back what `write_with_backup` changed: originals are restored, created files removed.
+// Refuses when any of the files was edited after the write, so those edits are not lost.
+pub fn restore(project: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    let edited = edited_since(project, snapshot);
+    if !edited.is_empty() {
+        return Err(io::Error::other(format!(
+            "edited since the apply: {}. Originals are in {}",
+            edited.join(", "),
+            snapshot.dir.display()
+        )));
+    }
+    for rel_path in &snapshot.modified {
+        let target = project.join(rel_path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(snapshot.dir.join(rel_path), &target)?;
+    }
+    for rel_path in &snapshot.created {
+        let target = project.join(rel_path);
+        if target.exists() {
+            fs::remove_file(&target)?;
+        }
+    }
+    Ok(())
+}
+
+// Files whose current content is not what `write_with_backup` left there
+fn edited_since(project: &Path, snapshot: &Snapshot) -> Vec<String> {
+    snapshot.written.iter()
+        .filter(|(rel_path, written)| {
+            let current = fs::read(project.join(rel_path)).ok().map(|bytes| hash(&bytes));
+            current != **written
+        })
+        .map(|(rel_path, _)| rel_path.clone())
+        .collect()
+}
+
+fn hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn new_backup_dir(project: &Path) -> PathBuf {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let base = project_store::data_path(project, BACKUPS_DIR);
+    let mut dir = base.join(now.to_string());
+    let mut n = 1;
+    while dir.exists() {
+        dir = base.join(format!("{}_{}", now, n));
+        n += 1;
+    }
+    dir
+}
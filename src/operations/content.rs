@@ -0,0 +1,54 @@
+use std::fs;
+use std::io;
+use std::borrow::Borrow;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::models::{file_node::FileNode, git_blob::GitBlob};
+use super::git;
+
+// Where the text of a file comes from: the disk, or the git object database when
+// the project is opened at a revision. Everything that exports or shows file
+// contents goes through here.
+pub fn read_bytes(file: &FileNode) -> io::Result<Vec<u8>> {
+    read_source(&file.path, file.blob.as_ref())
+}
+
+pub fn read_string(file: &FileNode) -> io::Result<String> {
+    String::from_utf8(read_bytes(file)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// For callers that only kept the path and blob (e.g. background loaders)
+pub fn read_source(path: &Path, blob: Option<&GitBlob>) -> io::Result<Vec<u8>> {
+    match blob {
+        Some(blob) => git::read_blob(&blob.repo_dir, &blob.id).map_err(io::Error::other),
+        None => fs::read(path),
+    }
+}
+
+// Reads many files in order, handing each readable one to `visit`. Blobs of one repository
+// come through a single git process instead of one per file. Stops early once `cancel` is set.
+pub fn for_each_file<F: Borrow<FileNode>>(files: &[F], cancel: &AtomicBool, mut visit: impl FnMut(&FileNode, Vec<u8>)) {
+    let repo_dir = files.first().and_then(|f| f.borrow().blob.as_ref()).map(|b| b.repo_dir.clone());
+    let batched = repo_dir.as_ref().filter(|dir| {
+        files.iter().all(|f| f.borrow().blob.as_ref().is_some_and(|b| &b.repo_dir == *dir))
+    });
+    let Some(repo_dir) = batched else {
+        for file in files {
+            if cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            if let Ok(bytes) = read_bytes(file.borrow()) {
+                visit(file.borrow(), bytes);
+            }
+        }
+        return;
+    };
+
+    let ids: Vec<String> = files.iter().filter_map(|f| f.borrow().blob.as_ref().map(|b| b.id.clone())).collect();
+    let _ = git::for_each_blob(repo_dir, &ids, cancel, |index, bytes| visit(files[index].borrow(), bytes.to_vec()));
+}
+
+// Blobs cannot disappear; disk files can
+pub fn exists(file: &FileNode) -> bool {
+    file.blob.is_some() || file.path.exists()
+}
@@ -1,15 +1,23 @@
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::Path;
-use crate::models::dir_node::DirNode;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+use crate::models::{dir_node::DirNode, file_node::FileNode, line_range::LineRange};
+use crate::models::export_options::{ConflictPolicy, ExportOptions, SeparateFilesOptions, TruncationLimit, TruncationOptions};
+use super::{content, git, selection, skeleton, transform};
+use super::transform::SourceLine;
 
 pub const DEFAULT_OUTPUT_FILENAME: &str = "full_code.txt";
+pub const MANIFEST_FILENAME: &str = "code_collector_manifest.tsv";
 
 pub fn calculate_stats(dir: &DirNode) -> (u64, usize) {
     let mut size = 0;
     let mut files = 0;
     for file in &dir.children_files {
         if file.selected {
-            size += file.size_bytes;
+            size += file.selected_bytes();
             files += 1;
         }
     }
@@ -21,35 +29,494 @@ pub fn calculate_stats(dir: &DirNode) -> (u64, usize) {
     (size, files)
 }
 
-pub fn collect_content_string(dir: &DirNode, content: &mut String) {
-    for file in &dir.children_files {
-        if file.selected {
-            if let Ok(code) = fs::read_to_string(&file.path) {
-                 content.push_str(&format!(
-                    "\n\n{}\nFILE: {}\nLANGUAGE: {}\n{}\n\n",
-                    "=".repeat(50), file.rel_path, file.extension, "=".repeat(50)
-                ));
-                content.push_str(&code);
+// Rough rule of thumb for LLM tokenizers: ~4 characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+// What the selection costs in a text export, and which files truncation cuts there
+#[derive(Default)]
+pub struct SelectionEstimate {
+    pub tokens: usize,
+    pub truncated: HashSet<String>, // Normalized rel_paths
+}
+
+// Token estimate of the selected files as they would be exported. For raw exports the file sizes
+// are enough; otherwise every file has to be read and rendered, so callers run this off the UI thread.
+// Truncated files are found by the same rendering, so the tree badge matches the export.
+pub fn estimate_selection(files: &[FileNode], options: &ExportOptions) -> SelectionEstimate {
+    let mut estimate = SelectionEstimate::default();
+    if options.diff.enabled {
+        estimate.tokens = files.iter().map(|file| estimate_tokens(&render_diff(file, options))).sum();
+        return estimate;
+    }
+    let (verbatim, rendered): (Vec<&FileNode>, Vec<&FileNode>) = files.iter().partition(|file| exports_verbatim(file, options));
+    estimate.tokens = verbatim.iter().map(|file| (file.size_bytes as usize).div_ceil(4)).sum();
+    content::for_each_file(&rendered, &AtomicBool::new(false), |file, bytes| {
+        if let Ok(code) = String::from_utf8(bytes) {
+            let (body, truncated) = render_body(&code, file, options);
+            estimate.tokens += estimate_tokens(&body);
+            if truncated {
+                estimate.truncated.insert(selection::normalize_rel_path(&file.rel_path));
             }
         }
+    });
+    estimate
+}
+
+fn exports_verbatim(file: &FileNode, options: &ExportOptions) -> bool {
+    options.is_raw() && !file.skeleton && file.line_ranges.is_empty()
+}
+
+// The text of one file as it appears in a text export (transforms, skeleton, ranges, line numbers)
+pub fn render_file_body(code: &str, file: &FileNode, options: &ExportOptions) -> String {
+    render_body(code, file, options).0
+}
+
+// The body and whether truncation cut it
+fn render_body(code: &str, file: &FileNode, options: &ExportOptions) -> (String, bool) {
+    if exports_verbatim(file, options) {
+        return (code.to_string(), false);
     }
-    for sub in &dir.children_dirs {
-        collect_content_string(sub, content);
+
+    let mut lines = transform::transform_lines(code, &file.extension, &options.transform);
+    if options.skeleton || file.skeleton {
+        if let Some(skeleton) = skeleton::skeletonize(&lines, &file.extension) {
+            lines = skeleton;
+        }
+    }
+
+    if !file.line_ranges.is_empty() {
+        lines = keep_ranges(lines, &file.line_ranges);
+    }
+    let truncated = options.truncation.enabled && exceeds_limit(&lines, &options.truncation);
+    if truncated {
+        lines = truncate_lines(lines, &options.truncation);
     }
+
+    let body = if options.line_numbers.enabled {
+        transform::number_lines(&lines, &options.line_numbers)
+    } else {
+        transform::join_lines(&lines, code.ends_with('\n'))
+    };
+    (body, truncated)
 }
 
-pub fn write_files_recursive(dir: &DirNode, base_target_path: &Path) -> std::io::Result<()> {
+// Unified diff of one file between the merge base of `base_ref` and the target (a ref or the
+// working tree), like `git diff base...target`, optionally followed by the full new version
+pub fn render_diff(file: &FileNode, options: &ExportOptions) -> String {
+    let diff = &options.diff;
+    // Run git next to the file, or from the project folder when the file only exists in a revision
+    let (dir, git_path) = match &file.blob {
+        Some(blob) => (blob.repo_dir.clone(), file.rel_path.clone()),
+        None => (
+            file.path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            file.name.clone(),
+        ),
+    };
+    // Changes made on `base_ref` since the branch point are not part of the diff
+    let base = git::merge_base(&dir, &diff.base_ref, &diff.target_ref).unwrap_or_else(|_| diff.base_ref.clone());
+    let old = match git::show_file(&dir, &git_path, &base) {
+        Ok(old) => old,
+        Err(e) => return format!("[cannot read {} at {}: {}]\n", file.rel_path, diff.base_ref.trim(), e),
+    };
+    let new = if diff.target_ref.trim().is_empty() {
+        content::read_string(file).ok()
+    } else {
+        match git::show_file(&dir, &git_path, &diff.target_ref) {
+            Ok(new) => new,
+            Err(e) => return format!("[cannot read {} at {}: {}]\n", file.rel_path, diff.target_ref.trim(), e),
+        }
+    };
+    if old.is_none() && new.is_none() {
+        return format!("[not present in {} or {}]\n", diff.base_ref.trim(), diff.target_label());
+    }
+    let old = old.unwrap_or_default();
+    let new_text = new.as_deref().unwrap_or_default();
+
+    let rel_path = file.rel_path.replace('\\', "/");
+    let mut out = if old == new_text {
+        format!("[no changes between {} and {}]\n", diff.base_ref.trim(), diff.target_label())
+    } else {
+        TextDiff::from_lines(old.as_str(), new_text)
+            .unified_diff()
+            .context_radius(diff.context_lines)
+            .header(&format!("a/{}", rel_path), &format!("b/{}", rel_path))
+            .to_string()
+    };
+
+    if diff.include_full_file {
+        match &new {
+            Some(code) => {
+                out.push_str(&format!("\n----- FULL FILE ({}) -----\n\n", diff.target_label()));
+                out.push_str(&render_file_body(code, file, &ExportOptions { diff: Default::default(), ..options.clone() }));
+            }
+            None => out.push_str(&format!("\n[deleted in {}]\n", diff.target_label())),
+        }
+    }
+    out
+}
+
+// The one rule for "this file gets truncated", counted on the lines as they will be exported
+fn exceeds_limit(lines: &[SourceLine], options: &TruncationOptions) -> bool {
+    match options.limit {
+        TruncationLimit::Lines => lines.len() > options.max,
+        TruncationLimit::Tokens => lines.iter().map(|l| estimate_tokens(&l.text)).sum::<usize>() > options.max,
+    }
+}
+
+// Keeps only the lines inside `ranges` (by original line number), each range
+// introduced by a "[lines a-b]" marker. Unnumbered lines follow their predecessor.
+fn keep_ranges(lines: Vec<SourceLine>, ranges: &[LineRange]) -> Vec<SourceLine> {
+    let mut out = Vec::new();
+    let mut current: Option<LineRange> = None;
+    let mut keeping = false;
+
+    for line in lines {
+        if let Some(n) = line.number {
+            let range = ranges.iter().find(|r| r.contains(n)).copied();
+            if let Some(r) = range {
+                if current != Some(r) {
+                    out.push(SourceLine { number: None, text: format!("[lines {}-{}]", r.start, r.end) });
+                    current = Some(r);
+                }
+            }
+            keeping = range.is_some();
+        }
+        if keeping {
+            out.push(line);
+        }
+    }
+    out
+}
+
+// Keeps the head and tail of an oversized file around an omission marker. With a line limit head
+// and tail are clamped to `max`; with a token limit the kept lines must fit in the budget, the head
+// getting its share of it first.
+fn truncate_lines(lines: Vec<SourceLine>, options: &TruncationOptions) -> Vec<SourceLine> {
+    if !exceeds_limit(&lines, options) {
+        return lines;
+    }
+    let keep = options.head_lines + options.tail_lines;
+    let (head, tail) = match options.limit {
+        TruncationLimit::Lines => {
+            let head = options.head_lines.min(options.max);
+            (head, options.tail_lines.min(options.max - head))
+        }
+        TruncationLimit::Tokens => {
+            let tokens: Vec<usize> = lines.iter().map(|l| estimate_tokens(&l.text)).collect();
+            let head_budget = (options.max * options.head_lines).checked_div(keep).unwrap_or(0);
+            let (head, used) = fit_lines(tokens.iter(), options.head_lines, head_budget);
+            let (tail, _) = fit_lines(tokens[head..].iter().rev(), options.tail_lines, options.max - used);
+            (head, tail)
+        }
+    };
+
+    let omitted = lines.len() - head - tail;
+    let mut lines = lines;
+    let tail = lines.split_off(lines.len() - tail);
+    lines.truncate(head);
+    lines.push(SourceLine { number: None, text: format!("[… {} lines omitted …]", omitted) });
+    lines.extend(tail);
+    lines
+}
+
+// How many of the first `max_lines` lines fit in `budget` tokens, and the tokens they use
+fn fit_lines<'a>(tokens: impl Iterator<Item = &'a usize>, max_lines: usize, budget: usize) -> (usize, usize) {
+    let mut count = 0;
+    let mut used = 0;
+    for &t in tokens.take(max_lines) {
+        if used + t > budget {
+            break;
+        }
+        used += t;
+        count += 1;
+    }
+    (count, used)
+}
+
+// A text export plus where each file starts in it
+#[derive(Default)]
+pub struct ExportDocument {
+    pub text: String,
+    pub files: Vec<(String, usize)>,     // (rel_path or section title, byte offset of its banner)
+    pub hashes: BTreeMap<String, String>, // rel_path -> sha256 of the source as it was read for this export
+    pub sections: usize,                  // Entries of `files` that are not project files
+}
+
+impl ExportDocument {
+    pub fn file_count(&self) -> usize {
+        self.files.len() - self.sections
+    }
+}
+
+pub fn build_export(root: &DirNode, options: &ExportOptions) -> ExportDocument {
+    let mut files = Vec::new();
+    selection::collect_selected_files(root, &mut files);
+    let mut document = ExportDocument::default();
+
+    if options.diff.enabled {
+        for file in &files {
+            let diff = render_diff(file, options);
+            let source = format!("DIFF: {} -> {}\n", options.diff.base_ref.trim(), options.diff.target_label());
+            push_file(&mut document, file, &source, &diff);
+            if let Ok(bytes) = content::read_bytes(file) {
+                document.hashes.insert(selection::normalize_rel_path(&file.rel_path), sha256(&bytes));
+            }
+        }
+    } else {
+        content::for_each_file(&files, &AtomicBool::new(false), |file, bytes| {
+            document.hashes.insert(selection::normalize_rel_path(&file.rel_path), sha256(&bytes));
+            if let Ok(code) = String::from_utf8(bytes) {
+                // Tells importers this is an outline, not the file, and that the gutter is not code
+                let mut extra = String::new();
+                if (options.skeleton || file.skeleton) && skeleton::supports(&file.extension) {
+                    extra.push_str("SKELETON: signatures only\n");
+                }
+                if options.line_numbers.enabled && !exports_verbatim(file, options) {
+                    extra.push_str("NUMBERED: line numbers are not part of the file\n");
+                }
+                push_file(&mut document, file, &extra, &render_file_body(&code, file, options));
+            }
+        });
+    }
+    document
+}
+
+// Appends one file with its banner; `extra` goes on its own lines under the language
+fn push_file(document: &mut ExportDocument, file: &FileNode, extra: &str, body: &str) {
+    let header = format!("FILE: {}\nLANGUAGE: {}\n{}", file.rel_path, file.extension, extra);
+    push_banner(document, &file.rel_path, &header, body);
+}
+
+// Appends a block that is not a project file (e.g. the list of deleted files), with the same banner
+pub fn push_section(document: &mut ExportDocument, title: &str, body: &str) {
+    push_banner(document, title, &format!("{}\n", title), body);
+    document.sections += 1;
+}
+
+// `header` lines sit between the two "=====" rules
+fn push_banner(document: &mut ExportDocument, title: &str, header: &str, body: &str) {
+    document.files.push((title.to_string(), document.text.len()));
+    document.text.push_str(&format!("\n\n{}\n{}{}\n\n", "=".repeat(50), header, "=".repeat(50)));
+    document.text.push_str(body);
+}
+
+pub fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[derive(Default)]
+pub struct ExportReport {
+    pub written: usize,
+    pub skipped: usize,
+    pub renamed: usize,
+    pub collisions: usize, // Two sources mapped to the same target (flattening); the later one was renamed
+    pub entries: Vec<ManifestEntry>,
+    pub targets: HashSet<PathBuf>, // Written during this run
+    pub hashes: BTreeMap<String, String>, // rel_path -> sha256 of the source as it was read
+}
+
+pub struct ManifestEntry {
+    pub source_rel_path: String,
+    pub target_rel_path: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+pub fn write_files_recursive(
+    dir: &DirNode,
+    base_target_path: &Path,
+    options: &SeparateFilesOptions,
+    report: &mut ExportReport,
+) -> std::io::Result<()> {
     for file in &dir.children_files {
         if file.selected {
-            let target_file_path = base_target_path.join(&file.rel_path);
+            let target_rel = if options.flatten {
+                flatten_rel_path(&file.rel_path)
+            } else {
+                file.rel_path.clone()
+            };
+            let mut target_file_path = base_target_path.join(&target_rel);
+
+            // Never let one file of this export overwrite another (a/b__c.rs vs a__b/c.rs when flattened)
+            if report.targets.contains(&target_file_path) {
+                target_file_path = next_free_path(&target_file_path);
+                report.collisions += 1;
+            } else if target_file_path.exists() {
+                match options.conflict_policy {
+                    ConflictPolicy::Overwrite => {}
+                    ConflictPolicy::Skip => {
+                        report.skipped += 1;
+                        continue;
+                    }
+                    ConflictPolicy::Rename => {
+                        target_file_path = next_free_path(&target_file_path);
+                        report.renamed += 1;
+                    }
+                }
+            }
+
             if let Some(parent) = target_file_path.parent() {
                 fs::create_dir_all(parent)?;
             }
-            fs::copy(&file.path, target_file_path)?;
+
+            let mut bytes = content::read_bytes(file)?;
+            report.hashes.insert(selection::normalize_rel_path(&file.rel_path), sha256(&bytes));
+            if !file.line_ranges.is_empty() {
+                // Only the chosen lines, with the same markers as the text export
+                let code = String::from_utf8_lossy(&bytes).to_string();
+                let lines = keep_ranges(transform::transform_lines(&code, &file.extension, &Default::default()), &file.line_ranges);
+                bytes = transform::join_lines(&lines, true).into_bytes();
+            }
+            fs::write(&target_file_path, &bytes)?;
+            if options.preserve_metadata && file.blob.is_none() {
+                copy_metadata(&file.path, &target_file_path)?;
+            }
+
+            report.written += 1;
+            report.targets.insert(target_file_path.clone());
+            report.entries.push(ManifestEntry {
+                source_rel_path: file.rel_path.clone(),
+                target_rel_path: target_file_path.strip_prefix(base_target_path)
+                    .unwrap_or(&target_file_path)
+                    .to_string_lossy()
+                    .to_string(),
+                size_bytes: bytes.len() as u64,
+                sha256: sha256(&bytes),
+            });
         }
     }
     for sub_dir in &dir.children_dirs {
-        write_files_recursive(sub_dir, base_target_path)?;
+        write_files_recursive(sub_dir, base_target_path, options, report)?;
+    }
+    Ok(())
+}
+
+pub fn write_manifest(base_target_path: &Path, report: &ExportReport) -> std::io::Result<()> {
+    let mut out = String::from("# Code Collector export manifest\n# sha256\tbytes\tsource\ttarget\n");
+    for entry in &report.entries {
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\n",
+            entry.sha256, entry.size_bytes, entry.source_rel_path, entry.target_rel_path
+        ));
+    }
+    fs::write(base_target_path.join(MANIFEST_FILENAME), out)
+}
+
+// Removes everything inside the target folder (but keeps the folder itself)
+pub fn clean_target_dir(target: &Path) -> std::io::Result<()> {
+    for entry in fs::read_dir(target)?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+fn flatten_rel_path(rel_path: &str) -> String {
+    rel_path.replace(['/', '\\'], "__")
+}
+
+// "tree.rs" -> "tree (1).rs", "tree (2).rs", ...
+fn next_free_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    let mut n = 1;
+    loop {
+        let candidate = path.with_file_name(format!("{} ({}){}", stem, n, ext));
+        if !candidate.exists() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+fn copy_metadata(source: &Path, target: &Path) -> std::io::Result<()> {
+    let meta = fs::metadata(source)?;
+    if let Ok(modified) = meta.modified() {
+        fs::File::options().write(true).open(target)?.set_modified(modified)?;
+    }
+    fs::set_permissions(target, meta.permissions())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(count: usize) -> Vec<SourceLine> {
+        (1..=count).map(|n| SourceLine { number: Some(n), text: format!("line {}", n) }).collect()
+    }
+
+    fn numbers(lines: &[SourceLine]) -> Vec<Option<usize>> {
+        lines.iter().map(|l| l.number).collect()
+    }
+
+    fn by_lines(max: usize, head_lines: usize, tail_lines: usize) -> TruncationOptions {
+        TruncationOptions { enabled: true, limit: TruncationLimit::Lines, max, head_lines, tail_lines }
+    }
+
+    #[test]
+    fn lines_within_the_limit_are_kept() {
+        let options = by_lines(10, 3, 2);
+        assert!(!exceeds_limit(&numbered(10), &options));
+        assert_eq!(truncate_lines(numbered(10), &options).len(), 10);
+    }
+
+    #[test]
+    fn lines_over_the_limit_keep_head_and_tail() {
+        let options = by_lines(10, 3, 2);
+        assert!(exceeds_limit(&numbered(11), &options));
+        let lines = truncate_lines(numbered(11), &options);
+        assert_eq!(numbers(&lines), vec![Some(1), Some(2), Some(3), None, Some(10), Some(11)]);
+        assert_eq!(lines[3].text, "[… 6 lines omitted …]");
+    }
+
+    #[test]
+    fn head_and_tail_are_clamped_to_the_limit() {
+        // Head + tail larger than max used to leave the file whole
+        let lines = truncate_lines(numbered(12), &by_lines(5, 4, 4));
+        assert_eq!(numbers(&lines), vec![Some(1), Some(2), Some(3), Some(4), None, Some(12)]);
+        let lines = truncate_lines(numbered(12), &by_lines(3, 10, 10));
+        assert_eq!(numbers(&lines), vec![Some(1), Some(2), Some(3), None]);
+    }
+
+    #[test]
+    fn token_limit_counts_the_exported_text() {
+        // "line N" is 6-7 chars, 2 tokens each
+        let options = TruncationOptions { enabled: true, limit: TruncationLimit::Tokens, max: 20, head_lines: 4, tail_lines: 4 };
+        assert!(!exceeds_limit(&numbered(10), &options));
+        assert!(exceeds_limit(&numbered(11), &options));
+        let lines = truncate_lines(numbered(30), &options);
+        let kept: usize = lines.iter().filter(|l| l.number.is_some()).map(|l| estimate_tokens(&l.text)).sum();
+        assert!(kept <= options.max);
+        assert_eq!(numbers(&lines), vec![Some(1), Some(2), Some(3), Some(4), None, Some(27), Some(28), Some(29), Some(30)]);
+    }
+
+    #[test]
+    fn badge_and_export_agree() {
+        let file = FileNode {
+            name: "a.rs".to_string(),
+            path: PathBuf::from("a.rs"),
+            rel_path: "a.rs".to_string(),
+            extension: "rs".to_string(),
+            selected: true,
+            size_bytes: 0,
+            line_count: None,
+            skeleton: false,
+            line_ranges: Vec::new(),
+            git_status: None,
+            blob: None,
+        };
+        let options = ExportOptions { truncation: by_lines(10, 3, 2), ..Default::default() };
+        for count in [9, 10, 11, 40] {
+            let code: String = (1..=count).map(|n| format!("line {}\n", n)).collect();
+            let (body, truncated) = render_body(&code, &file, &options);
+            assert_eq!(truncated, body.contains("lines omitted"), "{} lines", count);
+            assert_eq!(truncated, count > 10);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use crate::models::dir_node::DirNode;
+use crate::models::git_status::GitStatus;
+use super::selection::normalize_rel_path;
+
+// Runs local git inside the project folder
+fn run(project_path: &Path, args: &[&str]) -> Result<String, String> {
+    run_bytes(project_path, args).map(|out| String::from_utf8_lossy(&out).to_string())
+}
+
+fn run_bytes(project_path: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(project_path)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Cannot run git: {}", e))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(error.lines().next().unwrap_or("git failed").to_string());
+    }
+    Ok(output.stdout)
+}
+
+// Project-relative path -> status, for staged, unstaged and untracked files
+pub fn status(project_path: &Path) -> Result<HashMap<String, GitStatus>, String> {
+    // Porcelain paths are relative to the repository root, which may be above the project
+    let prefix = run(project_path, &["rev-parse", "--show-prefix"])?.trim().to_string();
+    let output = run(project_path, &["status", "--porcelain=v1", "-z", "--untracked-files=all", "--", "."])?;
+
+    let mut statuses = HashMap::new();
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        if record.len() < 4 {
+            continue;
+        }
+        let (code, path) = record.split_at(3);
+        let mut code = code.chars();
+        let (index, worktree) = (code.next().unwrap_or(' '), code.next().unwrap_or(' '));
+        if index == 'R' || index == 'C' {
+            records.next(); // Original path of the rename
+        }
+
+        let status = match (index, worktree) {
+            ('?', '?') => GitStatus::Untracked,
+            ('U', _) | (_, 'U') | ('A', 'A') | ('D', 'D') => GitStatus::Conflicted,
+            ('A', _) => GitStatus::Added,
+            ('R', _) | ('C', _) => GitStatus::Renamed,
+            (_, 'M') | (_, 'T') => GitStatus::Modified,
+            (_, 'D') | ('D', _) => continue, // Not on disk, so not in the tree
+            _ => GitStatus::Staged,
+        };
+        if let Some(rel_path) = path.strip_prefix(prefix.as_str()) {
+            statuses.insert(rel_path.to_string(), status);
+        }
+    }
+    Ok(statuses)
+}
+
+pub fn apply_status(dir: &mut DirNode, statuses: &HashMap<String, GitStatus>) {
+    for file in &mut dir.children_files {
+        file.git_status = statuses.get(&normalize_rel_path(&file.rel_path)).copied();
+    }
+    for sub in &mut dir.children_dirs {
+        apply_status(sub, statuses);
+    }
+}
+
+// Files changed on this branch: everything that differs from the merge base with
+// `base_ref` (committed, staged or not) plus untracked files. Deleted files are left out.
+pub fn changed_since(project_path: &Path, base_ref: &str) -> Result<Vec<String>, String> {
+    let base_ref = base_ref.trim();
+    if base_ref.is_empty() {
+        return Err("Enter a branch, tag or commit".to_string());
+    }
+    let merge_base = run(project_path, &["merge-base", base_ref, "HEAD"])?.trim().to_string();
+    let diff = run(project_path, &["diff", "--name-only", "--relative", "-z", "--diff-filter=d", &merge_base])?;
+    let untracked = run(project_path, &["ls-files", "--others", "--exclude-standard", "-z"])?;
+
+    let mut changed: Vec<String> = diff.split('\0').chain(untracked.split('\0'))
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect();
+    changed.sort();
+    changed.dedup();
+    Ok(changed)
+}
+
+// Commit where `target` (a ref, or HEAD when empty) branched off `base`
+pub fn merge_base(dir: &Path, base: &str, target: &str) -> Result<String, String> {
+    let target = if target.trim().is_empty() { "HEAD" } else { target.trim() };
+    run(dir, &["merge-base", base.trim(), target]).map(|id| id.trim().to_string())
+}
+
+// Contents of `rel_path` (relative to `dir`) at a revision. Ok(None) when the file does not exist there.
+pub fn show_file(dir: &Path, rel_path: &str, rev: &str) -> Result<Option<String>, String> {
+    match run(dir, &["show", &format!("{}:./{}", rev.trim(), rel_path.replace('\\', "/"))]) {
+        Ok(content) => Ok(Some(content)),
+        // Tell "no such file at that revision" apart from "no such revision"
+        Err(e) => match run(dir, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev.trim())]) {
+            Ok(_) => Ok(None),
+            Err(_) => Err(e),
+        },
+    }
+}
+
+// Full commit id of a branch, tag or commit
+pub fn resolve_commit(project_path: &Path, rev: &str) -> Result<String, String> {
+    let rev = rev.trim();
+    if rev.is_empty() {
+        return Err("Enter a branch, tag or commit".to_string());
+    }
+    run(project_path, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev)])
+        .map(|id| id.trim().to_string())
+        .map_err(|_| format!("Unknown revision: {}", rev))
+}
+
+// (path relative to the project folder, blob id, size) of every file at `rev`
+pub fn list_tree(project_path: &Path, rev: &str) -> Result<Vec<(String, String, u64)>, String> {
+    // Without --full-tree the listing is limited to, and relative to, the project folder
+    let output = run(project_path, &["ls-tree", "-r", "-l", "-z", rev])?;
+    let mut entries = Vec::new();
+    for record in output.split('\0').filter(|r| !r.is_empty()) {
+        let Some((meta, path)) = record.split_once('\t') else { continue; };
+        let fields: Vec<&str> = meta.split_whitespace().collect();
+        if let [_mode, "blob", id, size] = fields.as_slice() {
+            entries.push((path.to_string(), id.to_string(), size.parse().unwrap_or(0)));
+        }
+    }
+    Ok(entries)
+}
+
+pub fn read_blob(repo_dir: &Path, id: &str) -> Result<Vec<u8>, String> {
+    run_bytes(repo_dir, &["cat-file", "blob", id])
+}
+
+// Reads many blobs through a single `git cat-file --batch`, handing each to `visit` with its
+// index in `ids` (missing blobs are skipped). Stops early once `cancel` is set.
+pub fn for_each_blob(repo_dir: &Path, ids: &[String], cancel: &AtomicBool, mut visit: impl FnMut(usize, &[u8])) -> Result<(), String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(repo_dir)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Cannot run git: {}", e))?;
+
+    // Feed ids from another thread so neither pipe can fill up and block
+    let Some(mut stdin) = child.stdin.take() else {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err("git has no stdin".to_string());
+    };
+    let input: String = ids.iter().map(|id| format!("{}\n", id)).collect();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let result = match child.stdout.take() {
+        Some(stdout) => read_batch(BufReader::new(stdout), ids, cancel, &mut visit),
+        None => Err("git has no stdout".to_string()),
+    };
+    // Every exit path ends the process and the writer; a git that is still waiting for
+    // input or output after an error would otherwise stay around with its pipes
+    if result.is_err() {
+        let _ = child.kill();
+    }
+    let _ = writer.join();
+    let _ = child.wait();
+    result
+}
+
+fn read_batch(mut stdout: impl BufRead, ids: &[String], cancel: &AtomicBool, visit: &mut impl FnMut(usize, &[u8])) -> Result<(), String> {
+    let mut header = String::new();
+    for index in 0..ids.len() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err("Cancelled".to_string());
+        }
+        header.clear();
+        if stdout.read_line(&mut header).map_err(|e| e.to_string())? == 0 {
+            break;
+        }
+        // "<id> blob <size>", or "<id> missing"
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        let [_id, _kind, size] = fields.as_slice() else { continue; };
+        let size: usize = size.parse().map_err(|_| format!("Unexpected git output: {}", header.trim()))?;
+        let mut content = vec![0; size + 1]; // Content plus a trailing newline
+        stdout.read_exact(&mut content).map_err(|e| e.to_string())?;
+        visit(index, &content[..size]);
+    }
+    Ok(())
+}
+
+// Files that currently carry a status badge
+pub fn collect_changed(dir: &DirNode, out: &mut Vec<String>) {
+    for file in &dir.children_files {
+        if file.git_status.is_some() {
+            out.push(normalize_rel_path(&file.rel_path));
+        }
+    }
+    for sub in &dir.children_dirs {
+        collect_changed(sub, out);
+    }
+}
@@ -0,0 +1,790 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::atomic::AtomicBool;
+use regex::Regex;
+use crate::models::{dir_node::DirNode, file_node::FileNode};
+use super::selection::normalize_rel_path;
+use super::{content, transform};
+
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts", "vue", "svelte"];
+const JS_RESOLVE_SUFFIXES: &[&str] = &[
+    "", ".ts", ".tsx", ".d.ts", ".js", ".jsx", ".mjs", ".cjs", ".vue", ".svelte",
+    "/index.ts", "/index.tsx", "/index.js", "/index.jsx",
+];
+const PYTHON_SOURCE_ROOTS: &[&str] = &["", "src", "lib"];
+const MAX_INDEXED_BYTES: u64 = 2 * 1024 * 1024; // Larger files are generated or vendored
+
+// Everything needed to turn import statements into project files.
+// All paths are project-relative with '/' separators.
+pub struct ProjectIndex {
+    files: HashMap<String, FileNode>,
+    ts_base_url: Option<String>,
+    ts_aliases: Vec<(String, Vec<String>)>, // ("@/*", ["src/*"]), targets already joined with baseUrl
+    psr4: Vec<(String, Vec<String>)>,       // ("App\\", ["app/"])
+}
+
+impl ProjectIndex {
+    pub fn build(root_node: &DirNode) -> Self {
+        let mut files = HashMap::new();
+        collect_files(root_node, &mut files);
+
+        let mut index = Self {
+            files,
+            ts_base_url: None,
+            ts_aliases: Vec::new(),
+            psr4: Vec::new(),
+        };
+        index.load_tsconfig();
+        index.load_composer();
+        index
+    }
+
+    pub fn read(&self, rel_path: &str) -> Option<String> {
+        self.files.get(rel_path).and_then(|file| content::read_string(file).ok())
+    }
+
+    // Local files imported by `rel_path`
+    pub fn imports_of(&self, rel_path: &str, code: &str) -> Vec<String> {
+        let extension = extension_of(rel_path);
+        let code = analyzable(code, &extension);
+
+        let mut found: Vec<String> = match extension.as_str() {
+            "rs" => self.rust_imports(rel_path, &code),
+            "py" | "pyw" => self.python_imports(rel_path, &code),
+            "php" | "blade.php" => self.php_imports(rel_path, &code),
+            "c" | "h" | "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => self.c_includes(rel_path, &code),
+            ext if JS_EXTENSIONS.contains(&ext) => self.js_imports(rel_path, &code),
+            _ => Vec::new(),
+        };
+        found.retain(|dep| dep != rel_path);
+        found.sort();
+        found.dedup();
+        found
+    }
+
+    // Rust child modules `rel_path` declares with `mod x;` but never uses: the parent contains
+    // them rather than depending on them
+    pub fn submodules_of(&self, rel_path: &str, code: &str) -> Vec<String> {
+        if extension_of(rel_path) != "rs" {
+            return Vec::new();
+        }
+        let code = analyzable(code, "rs");
+        let used = self.rust_use_files(rel_path, &code);
+        let mut children = self.rust_mod_files(rel_path, &code);
+        children.retain(|child| !used.contains(child));
+        children
+    }
+
+    fn exists(&self, rel_path: &str) -> bool {
+        self.files.contains_key(rel_path)
+    }
+
+    // --- Rust: `mod x;`, `use crate::a::b`, `super::`, `self::` ---
+
+    fn rust_imports(&self, rel_path: &str, code: &str) -> Vec<String> {
+        let mut deps = self.rust_mod_files(rel_path, code);
+        deps.extend(self.rust_use_files(rel_path, code));
+        deps
+    }
+
+    // Files reached through `use` trees and inline `crate::`/`super::`/`self::` paths
+    fn rust_use_files(&self, rel_path: &str, code: &str) -> Vec<String> {
+        static USE_DECL: OnceLock<Regex> = OnceLock::new();
+        static INLINE_PATH: OnceLock<Regex> = OnceLock::new();
+        static ALIAS: OnceLock<Regex> = OnceLock::new();
+        let use_decl = USE_DECL.get_or_init(|| Regex::new(r"(?s)\buse\s+([^;]+);").unwrap());
+        let inline_path = INLINE_PATH.get_or_init(|| Regex::new(r"\b(?:crate|super|self)(?:::\w+)+").unwrap());
+        let alias = ALIAS.get_or_init(|| Regex::new(r"\s+as\s+\w+").unwrap());
+
+        let module_dir = rust_module_dir(rel_path);
+        let mut deps = Vec::new();
+
+        let mut paths = Vec::new();
+        for cap in use_decl.captures_iter(code) {
+            let tree = alias.replace_all(&cap[1], "");
+            expand_use_tree(&tree.split_whitespace().collect::<String>(), "", &mut paths);
+        }
+        paths.extend(inline_path.find_iter(code).map(|m| m.as_str().to_string()));
+
+        for path in paths {
+            let segments: Vec<&str> = path.split("::").filter(|s| !s.is_empty()).collect();
+            let Some((first, rest)) = segments.split_first() else { continue; };
+            let base = match *first {
+                "crate" => self.rust_crate_root(rel_path),
+                "self" => Some(module_dir.clone()),
+                "super" => rust_super_dir(rel_path),
+                _ => None,
+            };
+            if let Some(base) = base {
+                self.resolve_rust_segments(&base, rest, &mut deps);
+            }
+        }
+        deps
+    }
+
+    // Files of the `mod x;` declarations in `rel_path`
+    fn rust_mod_files(&self, rel_path: &str, code: &str) -> Vec<String> {
+        static MOD_DECL: OnceLock<Regex> = OnceLock::new();
+        let mod_decl = MOD_DECL.get_or_init(|| Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(\w+)\s*;").unwrap());
+
+        let module_dir = rust_module_dir(rel_path);
+        let mut files = Vec::new();
+        for cap in mod_decl.captures_iter(code) {
+            let name = &cap[1];
+            for candidate in [join(&module_dir, &format!("{}.rs", name)), join(&module_dir, &format!("{}/mod.rs", name))] {
+                if self.exists(&candidate) {
+                    files.push(candidate);
+                }
+            }
+        }
+        files
+    }
+
+    // Walks `a::b::c` from a module folder; every segment that is a file is a dependency
+    fn resolve_rust_segments(&self, base: &str, segments: &[&str], deps: &mut Vec<String>) {
+        let mut dir = base.to_string();
+        for segment in segments {
+            if *segment == "super" {
+                dir = parent(&dir);
+                continue;
+            }
+            let as_file = join(&dir, &format!("{}.rs", segment));
+            let as_mod = join(&dir, &format!("{}/mod.rs", segment));
+            if self.exists(&as_file) {
+                deps.push(as_file);
+            } else if self.exists(&as_mod) {
+                deps.push(as_mod);
+            } else {
+                break; // The rest are items inside the last module
+            }
+            dir = join(&dir, segment);
+        }
+    }
+
+    // Folder of the nearest lib.rs / main.rs above the file
+    fn rust_crate_root(&self, rel_path: &str) -> Option<String> {
+        let mut dir = parent(rel_path);
+        loop {
+            if self.exists(&join(&dir, "lib.rs")) || self.exists(&join(&dir, "main.rs")) {
+                return Some(dir);
+            }
+            if dir.is_empty() {
+                return None;
+            }
+            dir = parent(&dir);
+        }
+    }
+
+    // --- JS / TS: import, export from, require(), import() ---
+
+    fn js_imports(&self, rel_path: &str, code: &str) -> Vec<String> {
+        static SPECIFIER: OnceLock<Regex> = OnceLock::new();
+        let specifier = SPECIFIER.get_or_init(|| Regex::new(
+            r#"(?:\bfrom\s*|\bimport\s*\(?\s*|\brequire\s*\(\s*)['"]([^'"\n]+)['"]"#
+        ).unwrap());
+
+        let dir = parent(rel_path);
+        let mut deps = Vec::new();
+        for cap in specifier.captures_iter(code) {
+            let spec = &cap[1];
+            let bases: Vec<String> = if spec.starts_with('.') {
+                vec![join(&dir, spec)]
+            } else {
+                let mut bases: Vec<String> = self.ts_aliases.iter()
+                    .filter_map(|(pattern, targets)| match_alias(pattern, spec).map(|rest| (rest, targets)))
+                    .flat_map(|(rest, targets)| targets.iter().map(move |t| t.replace('*', &rest)))
+                    .collect();
+                if let Some(base_url) = &self.ts_base_url {
+                    bases.push(join(base_url, spec));
+                }
+                bases
+            };
+            if let Some(found) = bases.iter().find_map(|base| self.resolve_js(base)) {
+                deps.push(found);
+            }
+        }
+        deps
+    }
+
+    fn resolve_js(&self, base: &str) -> Option<String> {
+        let base = normalize(base);
+        // ESM TypeScript imports "./x.js" while the file on disk is "./x.ts"
+        let stripped = base.strip_suffix(".js").or_else(|| base.strip_suffix(".mjs")).map(str::to_string);
+        std::iter::once(base).chain(stripped)
+            .flat_map(|b| JS_RESOLVE_SUFFIXES.iter().map(move |suffix| format!("{}{}", b, suffix)))
+            .find(|candidate| self.exists(candidate))
+    }
+
+    fn load_tsconfig(&mut self) {
+        let Some(config) = ["tsconfig.json", "jsconfig.json"].iter().find_map(|name| self.read_json(name, "js")) else { return; };
+        let options = &config["compilerOptions"];
+        let base_url = options["baseUrl"].as_str().map(normalize).unwrap_or_default();
+
+        if let Some(paths) = options["paths"].as_object() {
+            for (pattern, targets) in paths {
+                let targets = targets.as_array().map(|t| {
+                    t.iter().filter_map(|v| v.as_str()).map(|v| join(&base_url, v)).collect()
+                }).unwrap_or_default();
+                self.ts_aliases.push((pattern.clone(), targets));
+            }
+        }
+        if options["baseUrl"].is_string() {
+            self.ts_base_url = Some(base_url);
+        }
+    }
+
+    // --- Python: import a.b, from .x import y ---
+
+    fn python_imports(&self, rel_path: &str, code: &str) -> Vec<String> {
+        static IMPORT: OnceLock<Regex> = OnceLock::new();
+        static FROM: OnceLock<Regex> = OnceLock::new();
+        let import = IMPORT.get_or_init(|| Regex::new(r"(?m)^\s*import\s+([\w., ]+)").unwrap());
+        // Only a parenthesized name list may span lines
+        let from = FROM.get_or_init(|| Regex::new(r"(?m)^\s*from\s+(\.*)([\w.]*)\s+import\s+(?:\(([^)]*)\)|([\w, *]+))").unwrap());
+
+        let mut deps = Vec::new();
+        for cap in import.captures_iter(code) {
+            for module in cap[1].split(',') {
+                let module = module.split_whitespace().next().unwrap_or_default();
+                self.resolve_python(&self.python_roots(rel_path), module, &mut deps);
+            }
+        }
+
+        for cap in from.captures_iter(code) {
+            let dots = cap[1].len();
+            let module = &cap[2];
+            let roots = if dots > 0 {
+                let mut dir = parent(rel_path);
+                for _ in 1..dots {
+                    dir = parent(&dir);
+                }
+                vec![dir]
+            } else {
+                self.python_roots(rel_path)
+            };
+
+            self.resolve_python(&roots, module, &mut deps);
+            // `from pkg import sub` may name submodules rather than attributes
+            let names = cap.get(3).or_else(|| cap.get(4)).map(|m| m.as_str()).unwrap_or_default();
+            for name in names.split([',', '\n']).filter_map(|n| n.split_whitespace().next()) {
+                let sub = if module.is_empty() { name.to_string() } else { format!("{}.{}", module, name) };
+                self.resolve_python(&roots, &sub, &mut deps);
+            }
+        }
+        deps
+    }
+
+    fn python_roots(&self, rel_path: &str) -> Vec<String> {
+        let mut roots: Vec<String> = PYTHON_SOURCE_ROOTS.iter().map(|r| r.to_string()).collect();
+        roots.push(parent(rel_path)); // Scripts importing siblings
+        roots
+    }
+
+    fn resolve_python(&self, roots: &[String], module: &str, deps: &mut Vec<String>) {
+        if module.is_empty() || module == "*" {
+            return;
+        }
+        let path = module.replace('.', "/");
+        for root in roots {
+            for candidate in [join(root, &format!("{}.py", path)), join(root, &format!("{}/__init__.py", path))] {
+                if self.exists(&candidate) {
+                    deps.push(candidate);
+                    return;
+                }
+            }
+        }
+    }
+
+    // --- PHP: use Namespace\Class (PSR-4), require/include ---
+
+    fn php_imports(&self, rel_path: &str, code: &str) -> Vec<String> {
+        static USE: OnceLock<Regex> = OnceLock::new();
+        static REQUIRE: OnceLock<Regex> = OnceLock::new();
+        let use_decl = USE.get_or_init(|| Regex::new(r"(?m)^\s*use\s+(?:function\s+|const\s+)?([^;]+);").unwrap());
+        let require = REQUIRE.get_or_init(|| Regex::new(
+            r#"\b(?:require|include)(?:_once)?\s*\(?\s*(?:__DIR__\s*\.\s*)?['"]([^'"]+)['"]"#
+        ).unwrap());
+
+        let dir = parent(rel_path);
+        let mut deps = Vec::new();
+
+        for cap in use_decl.captures_iter(code) {
+            let mut classes = Vec::new();
+            let body: String = cap[1].split_whitespace().collect::<Vec<_>>().join(" ");
+            match body.split_once('{') {
+                Some((prefix, group)) => {
+                    for item in group.trim_end_matches('}').split(',') {
+                        classes.push(format!("{}{}", prefix.trim(), item.trim()));
+                    }
+                }
+                None => classes.extend(body.split(',').map(|c| c.trim().to_string())),
+            }
+            for class in classes {
+                let class = class.split(" as ").next().unwrap_or_default().trim().trim_start_matches('\\');
+                if let Some(found) = self.resolve_php_class(class) {
+                    deps.push(found);
+                }
+            }
+        }
+
+        for cap in require.captures_iter(code) {
+            let target = cap[1].trim_start_matches('/');
+            for candidate in [join(&dir, target), normalize(target)] {
+                if self.exists(&candidate) {
+                    deps.push(candidate);
+                    break;
+                }
+            }
+        }
+        deps
+    }
+
+    fn resolve_php_class(&self, class: &str) -> Option<String> {
+        for (prefix, dirs) in &self.psr4 {
+            if let Some(rest) = class.strip_prefix(prefix.as_str()) {
+                for dir in dirs {
+                    let candidate = join(dir, &format!("{}.php", rest.replace('\\', "/")));
+                    if self.exists(&candidate) {
+                        return Some(candidate);
+                    }
+                }
+            }
+        }
+        // No composer.json: try the namespace as a path, Laravel-style lowercase root too
+        let path = format!("{}.php", class.replace('\\', "/"));
+        let mut lower_root = path.clone();
+        if let Some(first) = lower_root.split('/').next().map(str::to_lowercase) {
+            lower_root = format!("{}{}", first, &path[first.len()..]);
+        }
+        [path, lower_root].into_iter().find(|c| self.exists(c))
+    }
+
+    fn load_composer(&mut self) {
+        let Some(composer) = self.read_json("composer.json", "json") else { return; };
+        for section in ["autoload", "autoload-dev"] {
+            if let Some(map) = composer[section]["psr-4"].as_object() {
+                for (prefix, dirs) in map {
+                    let dirs = match dirs {
+                        serde_json::Value::String(d) => vec![normalize(d)],
+                        serde_json::Value::Array(list) => list.iter().filter_map(|d| d.as_str()).map(normalize).collect(),
+                        _ => continue,
+                    };
+                    self.psr4.push((prefix.clone(), dirs));
+                }
+            }
+        }
+    }
+
+    // --- C / C++: #include "x.h" ---
+
+    fn c_includes(&self, rel_path: &str, code: &str) -> Vec<String> {
+        static INCLUDE: OnceLock<Regex> = OnceLock::new();
+        let include = INCLUDE.get_or_init(|| Regex::new(r#"(?m)^\s*#\s*include\s*"([^"]+)""#).unwrap());
+
+        let dir = parent(rel_path);
+        let mut deps = Vec::new();
+        for cap in include.captures_iter(code) {
+            let target = &cap[1];
+            let direct = [join(&dir, target), normalize(target)].into_iter().find(|c| self.exists(c));
+            // Fall back to any project file ending in the include path (include dirs)
+            let found = direct.or_else(|| {
+                let suffix = format!("/{}", normalize(target));
+                let mut matches = self.files.keys().filter(|f| f.ends_with(&suffix));
+                match (matches.next(), matches.next()) {
+                    (Some(only), None) => Some(only.clone()),
+                    _ => None,
+                }
+            });
+            deps.extend(found);
+        }
+        deps
+    }
+
+    // JSON config files may contain comments and trailing commas
+    fn read_json(&self, name: &str, comment_syntax: &str) -> Option<serde_json::Value> {
+        static TRAILING_COMMA: OnceLock<Regex> = OnceLock::new();
+        let trailing_comma = TRAILING_COMMA.get_or_init(|| Regex::new(r",(\s*[}\]])").unwrap());
+
+        let text = self.read(name)?;
+        let text = transform::strip_comments(&text, comment_syntax);
+        serde_json::from_str(&trailing_comma.replace_all(&text, "$1")).ok()
+    }
+}
+
+// Import edges of the whole project in both directions, built once after a scan
+pub struct ImportGraph {
+    imports: HashMap<String, Vec<String>>,
+    importers: HashMap<String, Vec<String>>,
+    submodules: HashMap<String, Vec<String>>,
+}
+
+impl ImportGraph {
+    pub fn build(index: &ProjectIndex) -> Self {
+        let mut imports = HashMap::new();
+        let mut importers: HashMap<String, Vec<String>> = HashMap::new();
+        let mut submodules = HashMap::new();
+
+        let files: Vec<&FileNode> = index.files.iter()
+            .filter(|(rel_path, file)| has_imports(&extension_of(rel_path)) && file.size_bytes <= MAX_INDEXED_BYTES)
+            .map(|(_, file)| file)
+            .collect();
+        content::for_each_file(&files, &AtomicBool::new(false), |file, bytes| {
+            let Ok(code) = String::from_utf8(bytes) else { return; };
+            let rel_path = &normalize_rel_path(&file.rel_path);
+            let deps = index.imports_of(rel_path, &code);
+            for dep in &deps {
+                importers.entry(dep.clone()).or_default().push(rel_path.clone());
+            }
+            imports.insert(rel_path.clone(), deps);
+            let children = index.submodules_of(rel_path, &code);
+            if !children.is_empty() {
+                submodules.insert(rel_path.clone(), children);
+            }
+        });
+        for users in importers.values_mut() {
+            users.sort();
+        }
+        Self { imports, importers, submodules }
+    }
+
+    pub fn imports_of(&self, rel_path: &str) -> &[String] {
+        self.imports.get(rel_path).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn importers_of(&self, rel_path: &str) -> &[String] {
+        self.importers.get(rel_path).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn submodules_of(&self, rel_path: &str) -> &[String] {
+        self.submodules.get(rel_path).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+// Selected files that import local files which are not selected: (file, missing imports).
+// A Rust module only declaring a child with `mod x;` doesn't need the child's code.
+pub fn missing_context(graph: &ImportGraph, selected: &[String]) -> Vec<(String, Vec<String>)> {
+    let selected_set: HashSet<&str> = selected.iter().map(String::as_str).collect();
+    selected.iter()
+        .filter_map(|rel_path| {
+            let submodules = graph.submodules_of(rel_path);
+            let missing: Vec<String> = graph.imports_of(rel_path).iter()
+                .filter(|dep| !selected_set.contains(dep.as_str()) && !submodules.contains(dep))
+                .cloned()
+                .collect();
+            (!missing.is_empty()).then(|| (rel_path.clone(), missing))
+        })
+        .collect()
+}
+
+// Files `start` imports. `max_depth` None = all transitive dependencies.
+pub fn collect_dependencies(graph: &ImportGraph, start: &str, max_depth: Option<usize>) -> Vec<String> {
+    walk(start, max_depth, |rel_path| graph.imports_of(rel_path).to_vec())
+}
+
+// Files that import `start`, directly or through other files
+pub fn collect_dependents(graph: &ImportGraph, start: &str, max_depth: Option<usize>) -> Vec<String> {
+    walk(start, max_depth, |rel_path| graph.importers_of(rel_path).to_vec())
+}
+
+// Breadth-first walk; the start file itself is not part of the result
+fn walk(start: &str, max_depth: Option<usize>, mut next: impl FnMut(&str) -> Vec<String>) -> Vec<String> {
+    let mut seen: HashSet<String> = HashSet::from([start.to_string()]);
+    let mut queue = VecDeque::from([(start.to_string(), 0)]);
+    let mut result = Vec::new();
+
+    while let Some((rel_path, depth)) = queue.pop_front() {
+        if max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+        for found in next(&rel_path) {
+            if seen.insert(found.clone()) {
+                result.push(found.clone());
+                queue.push_back((found, depth + 1));
+            }
+        }
+    }
+    result
+}
+
+fn collect_files(dir: &DirNode, files: &mut HashMap<String, FileNode>) {
+    for file in &dir.children_files {
+        files.insert(normalize_rel_path(&file.rel_path), file.clone());
+    }
+    for sub in &dir.children_dirs {
+        collect_files(sub, files);
+    }
+}
+
+// `use a::{b, c::{d, e}}` -> a::b, a::c::d, a::c::e (whitespace and aliases already removed)
+fn expand_use_tree(tree: &str, prefix: &str, out: &mut Vec<String>) {
+    let Some(open) = tree.find('{') else {
+        out.push(format!("{}{}", prefix, tree.trim_end_matches("::")));
+        return;
+    };
+    let head = format!("{}{}", prefix, &tree[..open]);
+    let inner = &tree[open + 1..tree.rfind('}').unwrap_or(tree.len())];
+
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                expand_use_tree(&inner[start..i], &head, out);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < inner.len() {
+        expand_use_tree(&inner[start..], &head, out);
+    }
+}
+
+// Where child modules of a Rust file live
+fn rust_module_dir(rel_path: &str) -> String {
+    let dir = parent(rel_path);
+    let file_name = rel_path.rsplit('/').next().unwrap_or_default();
+    match file_name {
+        "mod.rs" | "lib.rs" | "main.rs" => dir,
+        _ => join(&dir, file_name.trim_end_matches(".rs")),
+    }
+}
+
+// Module folder that `super::` refers to
+fn rust_super_dir(rel_path: &str) -> Option<String> {
+    match rel_path.rsplit('/').next().unwrap_or_default() {
+        "lib.rs" | "main.rs" => None,
+        "mod.rs" => Some(parent(&parent(rel_path))),
+        _ => Some(parent(rel_path)),
+    }
+}
+
+// "@/*" matching "@/components/x" yields "components/x"
+fn match_alias(pattern: &str, spec: &str) -> Option<String> {
+    match pattern.split_once('*') {
+        Some((before, after)) => spec.strip_prefix(before)
+            .and_then(|rest| rest.strip_suffix(after))
+            .map(str::to_string),
+        None => (pattern == spec).then(String::new),
+    }
+}
+
+// Comments never hold imports. Rust and Python imports are never strings either, so those go
+// too; JS, PHP and C name their targets in strings, and text inside other strings still matches.
+fn analyzable(code: &str, extension: &str) -> String {
+    match extension {
+        "rs" | "py" | "pyw" => transform::strip_comments_and_strings(code, extension),
+        _ => transform::strip_comments(code, extension),
+    }
+}
+
+fn has_imports(extension: &str) -> bool {
+    matches!(extension, "rs" | "py" | "pyw" | "php" | "blade.php" | "c" | "h" | "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx")
+        || JS_EXTENSIONS.contains(&extension)
+}
+
+pub fn extension_of(rel_path: &str) -> String {
+    let lower = rel_path.to_lowercase();
+    if lower.ends_with(".blade.php") {
+        return "blade.php".to_string();
+    }
+    Path::new(&lower).extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+fn parent(rel_path: &str) -> String {
+    rel_path.rfind('/').map(|i| rel_path[..i].to_string()).unwrap_or_default()
+}
+
+fn join(dir: &str, rel: &str) -> String {
+    if dir.is_empty() { normalize(rel) } else { normalize(&format!("{}/{}", dir, rel)) }
+}
+
+// Resolves "." and ".." segments
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => { parts.pop(); }
+            other => parts.push(other),
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::*;
+
+    // A project on disk for ProjectIndex::build (tsconfig/composer are read from it), removed
+    // again once the index and every file's code are in memory
+    struct Project {
+        index: ProjectIndex,
+        code: HashMap<String, String>,
+    }
+
+    impl Project {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let dir = std::env::temp_dir().join(format!("code_collector_imports_{}_{}", name, std::process::id()));
+            let nodes = files.iter().map(|(rel_path, code)| {
+                let path = dir.join(rel_path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, code).unwrap();
+                FileNode {
+                    name: rel_path.rsplit('/').next().unwrap_or_default().to_string(),
+                    path,
+                    rel_path: rel_path.to_string(),
+                    extension: extension_of(rel_path),
+                    selected: false,
+                    size_bytes: code.len() as u64,
+                    line_count: None,
+                    skeleton: false,
+                    line_ranges: Vec::new(),
+                    git_status: None,
+                    blob: None,
+                }
+            }).collect();
+            let root = DirNode { name: name.to_string(), path: dir.clone(), children_dirs: Vec::new(), children_files: nodes };
+            let index = ProjectIndex::build(&root);
+            fs::remove_dir_all(&dir).unwrap();
+            let code = files.iter().map(|(rel_path, code)| (rel_path.to_string(), code.to_string())).collect();
+            Self { index, code }
+        }
+
+        fn imports(&self, rel_path: &str) -> Vec<String> {
+            self.index.imports_of(rel_path, &self.code[rel_path])
+        }
+
+        fn submodules(&self, rel_path: &str) -> Vec<String> {
+            self.index.submodules_of(rel_path, &self.code[rel_path])
+        }
+    }
+
+    fn rust_project() -> Project {
+        Project::new("rust", &[
+            ("src/main.rs", "mod app;\nmod models;\nmod ui;\n\nuse crate::ui::tree::render;\n"),
+            ("src/app.rs", "// use crate::models::b;\nconst HELP: &str = \"see crate::models::a\";\nlet raw = r#\"use crate::ui;\"#;\n"),
+            ("src/ui/mod.rs", "pub mod tree;\nuse super::app::App;\n"),
+            ("src/ui/tree.rs", "use super::super::app;\nuse crate::models::{a::A, b as bee};\n"),
+            ("src/models/mod.rs", "pub mod a;\npub mod b;\n"),
+            ("src/models/a.rs", "pub struct A;\n"),
+            ("src/models/b.rs", "fn f<'a>(x: &'a str) -> char { 'x' }\n"),
+        ])
+    }
+
+    #[test]
+    fn rust_mod_and_use() {
+        let project = rust_project();
+        assert_eq!(project.imports("src/main.rs"), vec!["src/app.rs", "src/models/mod.rs", "src/ui/mod.rs", "src/ui/tree.rs"]);
+        assert_eq!(project.imports("src/ui/mod.rs"), vec!["src/app.rs", "src/ui/tree.rs"]);
+    }
+
+    #[test]
+    fn rust_super_and_nested_use_trees() {
+        let project = rust_project();
+        assert_eq!(project.imports("src/ui/tree.rs"), vec!["src/app.rs", "src/models/a.rs", "src/models/b.rs", "src/models/mod.rs"]);
+    }
+
+    #[test]
+    fn rust_paths_in_comments_and_strings_are_ignored() {
+        assert!(rust_project().imports("src/app.rs").is_empty());
+    }
+
+    #[test]
+    fn rust_declared_but_unused_modules_are_submodules() {
+        let project = rust_project();
+        assert_eq!(project.submodules("src/main.rs"), vec!["src/app.rs", "src/models/mod.rs"]);
+        assert_eq!(project.submodules("src/models/mod.rs"), vec!["src/models/a.rs", "src/models/b.rs"]);
+    }
+
+    #[test]
+    fn use_trees() {
+        let mut out = Vec::new();
+        expand_use_tree("crate::a::{b,c::{d,e},f::*,self}", "", &mut out);
+        assert_eq!(out, vec!["crate::a::b", "crate::a::c::d", "crate::a::c::e", "crate::a::f::*", "crate::a::self"]);
+        let mut out = Vec::new();
+        expand_use_tree("super::x::", "", &mut out);
+        assert_eq!(out, vec!["super::x"]);
+    }
+
+    fn js_project() -> Project {
+        Project::new("js", &[
+            ("tsconfig.json", "{\n  // aliases\n  \"compilerOptions\": {\n    \"baseUrl\": \".\",\n    \"paths\": { \"@/*\": [\"src/*\"], },\n  },\n}\n"),
+            ("src/app.ts", "import x from './util';\nimport { api } from '@/lib/api.js';\nconst config = require(\"../config\");\nconst Lazy = import('./lazy');\nimport 'react';\n// import './commented';\n"),
+            ("src/strings.ts", "const help = \"import './util'\";\n"),
+            ("src/util.ts", ""),
+            ("src/commented.ts", ""),
+            ("src/lib/api.ts", ""),
+            ("src/lazy.tsx", ""),
+            ("config/index.js", ""),
+        ])
+    }
+
+    #[test]
+    fn js_relative_alias_require_and_dynamic_import() {
+        assert_eq!(js_project().imports("src/app.ts"), vec!["config/index.js", "src/lazy.tsx", "src/lib/api.ts", "src/util.ts"]);
+    }
+
+    #[test]
+    fn js_specifiers_inside_other_strings_still_match() {
+        // Known limitation: JS names its imports in strings, so strings are not blanked
+        assert_eq!(js_project().imports("src/strings.ts"), vec!["src/util.ts"]);
+    }
+
+    #[test]
+    fn python_absolute_and_relative_imports() {
+        let project = Project::new("python", &[
+            ("pkg/__init__.py", ""),
+            ("pkg/core.py", "\"\"\"\nfrom pkg import secret\n\"\"\"\nfrom . import helpers\nfrom ..shared.db import connect\nfrom .. import (\n    tools,\n)\nimport pkg.models, os\n"),
+            ("pkg/helpers.py", ""),
+            ("pkg/models.py", ""),
+            ("pkg/secret.py", ""),
+            ("shared/db.py", ""),
+            ("tools/__init__.py", ""),
+        ]);
+        assert_eq!(project.imports("pkg/core.py"), vec!["pkg/helpers.py", "pkg/models.py", "shared/db.py", "tools/__init__.py"]);
+    }
+
+    #[test]
+    fn php_psr4_groups_and_require() {
+        let project = Project::new("php", &[
+            ("composer.json", r#"{"autoload": {"psr-4": {"App\\": "app/"}}}"#),
+            ("app/Http/Controller.php", "<?php\nnamespace App\\Http;\nuse App\\Models\\{User, Post as P};\nuse App\\Support\\Str;\nrequire_once __DIR__ . '/helpers.php';\n"),
+            ("app/Http/helpers.php", ""),
+            ("app/Models/User.php", ""),
+            ("app/Models/Post.php", ""),
+            ("app/Support/Str.php", ""),
+        ]);
+        assert_eq!(project.imports("app/Http/Controller.php"),
+            vec!["app/Http/helpers.php", "app/Models/Post.php", "app/Models/User.php", "app/Support/Str.php"]);
+    }
+
+    #[test]
+    fn c_includes_relative_and_through_include_dirs() {
+        let project = Project::new("c", &[
+            ("src/main.c", "#include <stdio.h>\n#include \"util.h\"\n#include \"net/socket.h\"\n"),
+            ("src/util.h", ""),
+            ("include/net/socket.h", ""),
+        ]);
+        assert_eq!(project.imports("src/main.c"), vec!["include/net/socket.h", "src/util.h"]);
+    }
+
+    #[test]
+    fn dependency_walk_depths() {
+        let project = rust_project();
+        let mut imports = HashMap::new();
+        for rel_path in project.code.keys() {
+            imports.insert(rel_path.clone(), project.imports(rel_path));
+        }
+        let graph = ImportGraph { imports, importers: HashMap::new(), submodules: HashMap::new() };
+        assert_eq!(collect_dependencies(&graph, "src/ui/mod.rs", Some(1)), vec!["src/app.rs", "src/ui/tree.rs"]);
+        let mut all = collect_dependencies(&graph, "src/ui/mod.rs", None);
+        all.sort();
+        assert_eq!(all, vec!["src/app.rs", "src/models/a.rs", "src/models/b.rs", "src/models/mod.rs", "src/ui/tree.rs"]);
+    }
+}
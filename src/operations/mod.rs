@@ -1,3 +1,14 @@
 pub mod export;
 pub mod selection;
-pub mod search;
\ No newline at end of file
+pub mod search;
+pub mod transform;
+pub mod skeleton;
+pub mod project_store;
+pub mod pattern;
+pub mod imports;
+pub mod git;
+pub mod content;
+pub mod response;
+pub mod backup;
+pub mod patch;
+pub mod tracking;
@@ -0,0 +1,375 @@
+use std::sync::OnceLock;
+use regex::Regex;
+use super::transform;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HunkLine {
+    Context(String),
+    Add(String),
+    Remove(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Hunk {
+    pub header: String,
+    pub old_start: usize, // 1-based, as in "@@ -12,5 +12,6 @@"; only a hint for where to look
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    // Lines the hunk expects to find in the file
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|l| match l {
+            HunkLine::Context(t) | HunkLine::Remove(t) => Some(t.as_str()),
+            HunkLine::Add(_) => None,
+        }).collect()
+    }
+
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|l| match l {
+            HunkLine::Context(t) | HunkLine::Add(t) => Some(t.as_str()),
+            HunkLine::Remove(_) => None,
+        }).collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FilePatch {
+    pub old_path: Option<String>, // None = /dev/null (new file)
+    pub new_path: Option<String>, // None = /dev/null (deleted file)
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    pub fn path(&self) -> &str {
+        self.new_path.as_deref().or(self.old_path.as_deref()).unwrap_or_default()
+    }
+}
+
+// Where a hunk landed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HunkFit {
+    Exact { line: usize },                      // 1-based line of the first old line
+    Offset { line: usize, offset: isize },      // Found away from the header position
+    Fuzzy { line: usize, fuzz: usize },         // Only after dropping context lines
+    Failed,
+}
+
+const MAX_FUZZ: usize = 2;
+
+// Reads one or more unified diffs. Prose and code fences around them are ignored.
+// A hunk ends when its header counts are used up; models get the counts wrong, so
+// when they don't match the body the hunk runs to the first line that is not a diff line.
+pub fn parse_patches(text: &str) -> Vec<FilePatch> {
+    static HUNK: OnceLock<Regex> = OnceLock::new();
+    let hunk_header = HUNK.get_or_init(|| Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+\d+(?:,(\d+))? @@").unwrap());
+
+    let text = text.replace("\r\n", "\n");
+    let lines: Vec<&str> = text.lines().collect();
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if line.starts_with("--- ") && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ ")) {
+            patches.push(FilePatch {
+                old_path: diff_path(&line[4..]),
+                new_path: diff_path(&lines[i + 1][4..]),
+                hunks: Vec::new(),
+            });
+            i += 2;
+            continue;
+        }
+
+        if let (Some(cap), Some(patch)) = (hunk_header.captures(line), patches.last_mut()) {
+            // "@@ -3 +3 @@" means one line
+            let count = |group: usize| cap.get(group).map_or(Some(1), |m| m.as_str().parse().ok());
+            let counted = count(2).zip(count(3)).and_then(|(old, new)| read_counted(&lines, i + 1, old, new));
+            let (body, next) = counted.unwrap_or_else(|| read_until_prose(&lines, i + 1));
+            patch.hunks.push(Hunk {
+                header: line.to_string(),
+                old_start: cap[1].parse().unwrap_or(1),
+                lines: body,
+            });
+            i = next;
+            continue;
+        }
+        i += 1;
+    }
+    patches.retain(|p| !p.hunks.is_empty());
+    patches
+}
+
+// One body line; None for lines that cannot be part of a hunk
+fn hunk_line(body: &str) -> Option<Option<HunkLine>> {
+    match body.chars().next() {
+        Some('+') => Some(Some(HunkLine::Add(body[1..].to_string()))),
+        Some('-') => Some(Some(HunkLine::Remove(body[1..].to_string()))),
+        Some(' ') => Some(Some(HunkLine::Context(body[1..].to_string()))),
+        Some('\\') => Some(None), // "\ No newline at end of file"
+        None => Some(Some(HunkLine::Context(String::new()))), // Editors strip the lone space
+        _ => None,
+    }
+}
+
+fn starts_file_header(lines: &[&str], i: usize) -> bool {
+    lines[i].starts_with("--- ") && lines.get(i + 1).is_some_and(|n| n.starts_with("+++ "))
+}
+
+// Exactly `old` old-side and `new` new-side lines. None when the body disagrees with the counts.
+fn read_counted(lines: &[&str], start: usize, mut old: usize, mut new: usize) -> Option<(Vec<HunkLine>, usize)> {
+    let mut body = Vec::new();
+    let mut i = start;
+    while old > 0 || new > 0 {
+        let line = lines.get(i)?;
+        if line.starts_with("@@") || starts_file_header(lines, i) {
+            return None;
+        }
+        match hunk_line(line)? {
+            Some(HunkLine::Context(t)) if old > 0 && new > 0 => { old -= 1; new -= 1; body.push(HunkLine::Context(t)); }
+            Some(HunkLine::Remove(t)) if old > 0 => { old -= 1; body.push(HunkLine::Remove(t)); }
+            Some(HunkLine::Add(t)) if new > 0 => { new -= 1; body.push(HunkLine::Add(t)); }
+            None => {}
+            Some(_) => return None,
+        }
+        i += 1;
+    }
+    if lines.get(i).is_some_and(|l| l.starts_with('\\')) {
+        i += 1;
+    }
+    Some((body, i))
+}
+
+// Every diff-looking line up to the next hunk, file header or line of prose
+fn read_until_prose(lines: &[&str], start: usize) -> (Vec<HunkLine>, usize) {
+    let mut body = Vec::new();
+    let mut i = start;
+    while i < lines.len() && !lines[i].starts_with("@@") && !starts_file_header(lines, i) {
+        let Some(line) = hunk_line(lines[i]) else { break; };
+        body.extend(line);
+        i += 1;
+    }
+    // Trailing blank "context" is usually the gap before the next diff
+    while matches!(body.last(), Some(HunkLine::Context(t)) if t.is_empty()) {
+        body.pop();
+    }
+    (body, i)
+}
+
+// "a/src/main.rs\t2024-01-01" -> "src/main.rs"; "/dev/null" -> None
+fn diff_path(raw: &str) -> Option<String> {
+    let path = raw.split('\t').next().unwrap_or_default().trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path.strip_prefix("a/").or_else(|| path.strip_prefix("b/")).unwrap_or(path);
+    Some(path.to_string())
+}
+
+// Finds where a hunk fits in `lines`, searching outward from its header position.
+// `shift` is how far earlier hunks moved things.
+pub fn locate_hunk(lines: &[&str], hunk: &Hunk, shift: isize) -> HunkFit {
+    let old = hunk.old_lines();
+    let expected = (hunk.old_start as isize - 1 + shift).max(0) as usize;
+
+    for fuzz in 0..=MAX_FUZZ {
+        let (lead, trail) = context_trim(hunk, fuzz);
+        if lead + trail > old.len() || (fuzz > 0 && lead + trail == 0) {
+            break;
+        }
+        let wanted = &old[lead..old.len() - trail];
+        if let Some(pos) = search_outward(lines, wanted, expected + lead) {
+            let line = pos - lead + 1;
+            return match (fuzz, pos as isize - (expected + lead) as isize) {
+                (0, 0) => HunkFit::Exact { line },
+                (0, offset) => HunkFit::Offset { line, offset },
+                _ => HunkFit::Fuzzy { line, fuzz },
+            };
+        }
+    }
+    HunkFit::Failed
+}
+
+// How many leading / trailing context lines to ignore at a fuzz level
+fn context_trim(hunk: &Hunk, fuzz: usize) -> (usize, usize) {
+    let leading = hunk.lines.iter().take_while(|l| matches!(l, HunkLine::Context(_))).count();
+    let trailing = hunk.lines.iter().rev().take_while(|l| matches!(l, HunkLine::Context(_))).count();
+    (leading.min(fuzz), trailing.min(fuzz))
+}
+
+// Nearest position of `wanted` to `expected`; trailing whitespace is ignored
+fn search_outward(lines: &[&str], wanted: &[&str], expected: usize) -> Option<usize> {
+    if wanted.is_empty() {
+        return Some(expected.min(lines.len()));
+    }
+    if wanted.len() > lines.len() {
+        return None;
+    }
+    let last = lines.len() - wanted.len();
+    let matches_at = |pos: usize| lines[pos..pos + wanted.len()].iter().zip(wanted).all(|(a, b)| a.trim_end() == b.trim_end());
+    let expected = expected.min(last);
+    for distance in 0..=last {
+        if expected >= distance && matches_at(expected - distance) {
+            return Some(expected - distance);
+        }
+        if distance > 0 && expected + distance <= last && matches_at(expected + distance) {
+            return Some(expected + distance);
+        }
+    }
+    None
+}
+
+// Applies the hunks (in file order) to `original`. Failed hunks are skipped and
+// reported; the rest are applied. The file keeps its line endings.
+pub fn apply_hunks(original: &str, hunks: &[&Hunk]) -> (String, Vec<HunkFit>) {
+    let mut lines: Vec<String> = original.lines().map(str::to_string).collect();
+    let mut fits = Vec::new();
+    let mut shift = 0isize;
+
+    for hunk in hunks {
+        let view: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let fit = locate_hunk(&view, hunk, shift);
+        fits.push(fit);
+        let (line, fuzz) = match fit {
+            HunkFit::Exact { line } | HunkFit::Offset { line, .. } => (line, 0),
+            HunkFit::Fuzzy { line, fuzz } => (line, fuzz),
+            HunkFit::Failed => continue,
+        };
+
+        // With fuzz the dropped context lines stay as they are in the file
+        let (lead, trail) = context_trim(hunk, fuzz);
+        let old = hunk.old_lines();
+        let new = hunk.new_lines();
+        let start = line - 1 + lead;
+        let end = (start + old.len() - lead - trail).min(lines.len());
+        let replacement: Vec<String> = new[lead..new.len() - trail].iter().map(|s| s.to_string()).collect();
+        // Later hunks move by this hunk's drift plus the lines it added or removed
+        shift = line as isize - hunk.old_start.max(1) as isize + replacement.len() as isize - (end - start) as isize;
+        lines.splice(start..end, replacement);
+    }
+
+    let eol = transform::line_ending(original);
+    let mut out = lines.join(eol);
+    if !lines.is_empty() && (original.ends_with('\n') || original.is_empty()) {
+        out.push_str(eol);
+    }
+    (out, fits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+
+    fn apply(original: &str, patch: &str) -> (String, Vec<HunkFit>) {
+        let patches = parse_patches(patch);
+        let hunks: Vec<&Hunk> = patches[0].hunks.iter().collect();
+        apply_hunks(original, &hunks)
+    }
+
+    #[test]
+    fn exact() {
+        let (text, fits) = apply(FILE, "--- a/f.txt\n+++ b/f.txt\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n");
+        assert_eq!(text, "a\nb\nC\nd\ne\nf\ng\nh\ni\nj\n");
+        assert_eq!(fits, vec![HunkFit::Exact { line: 2 }]);
+    }
+
+    #[test]
+    fn prose_after_an_unfenced_diff() {
+        let patch = "--- a/f.txt\n+++ b/f.txt\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n\n- first, I renamed c\n- then I checked d\n";
+        let (text, fits) = apply(FILE, patch);
+        assert_eq!(text, "a\nb\nC\nd\ne\nf\ng\nh\ni\nj\n");
+        assert_eq!(fits, vec![HunkFit::Exact { line: 2 }]);
+    }
+
+    #[test]
+    fn wrong_counts_fall_back_to_the_body() {
+        let (text, fits) = apply(FILE, "--- a/f.txt\n+++ b/f.txt\n@@ -2,7 +2,9 @@\n b\n-c\n+C\n d\n");
+        assert_eq!(text, "a\nb\nC\nd\ne\nf\ng\nh\ni\nj\n");
+        assert_eq!(fits, vec![HunkFit::Exact { line: 2 }]);
+    }
+
+    #[test]
+    fn offset() {
+        let original = format!("x\ny\nz\n{}", FILE);
+        let (text, fits) = apply(&original, "--- a/f.txt\n+++ b/f.txt\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n");
+        assert_eq!(text, format!("x\ny\nz\n{}", "a\nb\nC\nd\ne\nf\ng\nh\ni\nj\n"));
+        assert_eq!(fits, vec![HunkFit::Offset { line: 5, offset: 3 }]);
+    }
+
+    #[test]
+    fn fuzz_one_keeps_the_file_context() {
+        let original = FILE.replace("b\n", "B\n");
+        let (text, fits) = apply(&original, "--- a/f.txt\n+++ b/f.txt\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n");
+        assert_eq!(text, "a\nB\nC\nd\ne\nf\ng\nh\ni\nj\n");
+        assert_eq!(fits, vec![HunkFit::Fuzzy { line: 2, fuzz: 1 }]);
+    }
+
+    #[test]
+    fn fuzz_two() {
+        let original = FILE.replace("c\n", "cc\n");
+        let (text, fits) = apply(&original, "--- a/f.txt\n+++ b/f.txt\n@@ -2,5 +2,5 @@\n b\n c\n-d\n+D\n e\n f\n");
+        assert_eq!(text, "a\nb\ncc\nD\ne\nf\ng\nh\ni\nj\n");
+        assert_eq!(fits, vec![HunkFit::Fuzzy { line: 2, fuzz: 2 }]);
+    }
+
+    #[test]
+    fn failed_hunk_is_skipped() {
+        let patch = "--- a/f.txt\n+++ b/f.txt\n@@ -3,1 +3,1 @@\n-zzz\n+yyy\n@@ -8,1 +8,1 @@\n-h\n+H\n";
+        let (text, fits) = apply(FILE, patch);
+        assert_eq!(text, "a\nb\nc\nd\ne\nf\ng\nH\ni\nj\n");
+        assert_eq!(fits, vec![HunkFit::Failed, HunkFit::Exact { line: 8 }]);
+    }
+
+    #[test]
+    fn multiple_hunks_with_drift() {
+        // One extra line on top, and the first hunk adds two more before the second one
+        let original = format!("x\n{}", FILE);
+        let patch = "--- a/f.txt\n+++ b/f.txt\n@@ -2,2 +2,4 @@\n b\n+new1\n+new2\n c\n@@ -7,3 +9,3 @@\n g\n-h\n+H\n i\n";
+        let (text, fits) = apply(&original, patch);
+        assert_eq!(text, "x\na\nb\nnew1\nnew2\nc\nd\ne\nf\ng\nH\ni\nj\n");
+        assert_eq!(fits, vec![HunkFit::Offset { line: 3, offset: 1 }, HunkFit::Exact { line: 10 }]);
+    }
+
+    #[test]
+    fn wrong_hunk_counts_are_ignored() {
+        let (text, fits) = apply(FILE, "--- a/f.txt\n+++ b/f.txt\n@@ -2,10 +2,1 @@\n b\n-c\n+C\n d\n");
+        assert_eq!(text, "a\nb\nC\nd\ne\nf\ng\nh\ni\nj\n");
+        assert_eq!(fits, vec![HunkFit::Exact { line: 2 }]);
+    }
+
+    #[test]
+    fn new_and_deleted_files() {
+        let patches = parse_patches("--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n+x\n+y\n--- a/old.txt\n+++ /dev/null\n@@ -1,3 +0,0 @@\n-a\n-b\n-c\n");
+        assert_eq!(patches.len(), 2);
+        assert_eq!((patches[0].old_path.as_deref(), patches[0].path()), (None, "new.txt"));
+        assert_eq!((patches[1].new_path.as_deref(), patches[1].path()), (None, "old.txt"));
+
+        let (created, _) = apply_hunks("", &[&patches[0].hunks[0]]);
+        assert_eq!(created, "x\ny\n");
+        let (deleted, fits) = apply_hunks("a\nb\nc\n", &[&patches[1].hunks[0]]);
+        assert_eq!(deleted, "");
+        assert_eq!(fits, vec![HunkFit::Exact { line: 1 }]);
+    }
+
+    #[test]
+    fn prose_and_fences_around_the_diff() {
+        let text = "Apply this:\n\n```diff\n--- a/src/f.txt\n+++ b/src/f.txt\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n```\n\nThen rebuild.\n";
+        let patches = parse_patches(text);
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].path(), "src/f.txt");
+        assert_eq!(patches[0].hunks[0].lines, vec![
+            HunkLine::Context("b".to_string()),
+            HunkLine::Remove("c".to_string()),
+            HunkLine::Add("C".to_string()),
+            HunkLine::Context("d".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn crlf_files_keep_their_line_endings() {
+        let (text, _) = apply("a\r\nb\r\nc\r\n", "--- a/f.txt\r\n+++ b/f.txt\r\n@@ -1,3 +1,3 @@\r\n a\r\n-b\r\n+B\r\n c\r\n");
+        assert_eq!(text, "a\r\nB\r\nc\r\n");
+    }
+}
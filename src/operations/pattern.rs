@@ -0,0 +1,89 @@
+use globset::{Glob, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use crate::models::dir_node::DirNode;
+use super::selection::normalize_rel_path;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PatternKind {
+    Extensions, // "rs, toml"
+    Glob,       // "src/**/handlers/*.ts"
+    Regex,      // "^src/.*_test\.go$"
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PatternAction {
+    Add,
+    Remove,
+    Replace,
+}
+
+pub enum PathMatcher {
+    Extensions(Vec<String>),
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl PathMatcher {
+    pub fn compile(kind: PatternKind, pattern: &str, case_sensitive: bool) -> Result<Self, String> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Err("Enter a pattern".to_string());
+        }
+        match kind {
+            PatternKind::Extensions => Ok(PathMatcher::Extensions(
+                pattern.split([',', ' '])
+                    .map(|e| e.trim().trim_start_matches("*.").trim_start_matches('.').to_lowercase())
+                    .filter(|e| !e.is_empty())
+                    .collect(),
+            )),
+            PatternKind::Glob => globset::GlobBuilder::new(pattern)
+                .literal_separator(true) // `*` stays within a folder, `**` crosses them
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map(|g: Glob| PathMatcher::Glob(g.compile_matcher()))
+                .map_err(|e| e.to_string()),
+            PatternKind::Regex => RegexBuilder::new(pattern)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map(PathMatcher::Regex)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    // `rel_path` uses '/' separators
+    pub fn matches(&self, rel_path: &str, extension: &str) -> bool {
+        match self {
+            PathMatcher::Extensions(exts) => exts.iter().any(|e| e == extension),
+            PathMatcher::Glob(glob) => glob.is_match(rel_path),
+            PathMatcher::Regex(regex) => regex.is_match(rel_path),
+        }
+    }
+}
+
+pub fn count_matches(dir: &DirNode, matcher: &PathMatcher) -> usize {
+    let own = dir.children_files.iter()
+        .filter(|f| matcher.matches(&normalize_rel_path(&f.rel_path), &f.extension))
+        .count();
+    own + dir.children_dirs.iter().map(|sub| count_matches(sub, matcher)).sum::<usize>()
+}
+
+// Returns how many files changed their selection state
+pub fn apply_pattern(dir: &mut DirNode, matcher: &PathMatcher, action: PatternAction) -> usize {
+    let mut changed = 0;
+    for file in &mut dir.children_files {
+        let is_match = matcher.matches(&normalize_rel_path(&file.rel_path), &file.extension);
+        let new_state = match action {
+            PatternAction::Add => file.selected || is_match,
+            PatternAction::Remove => file.selected && !is_match,
+            PatternAction::Replace => is_match,
+        };
+        if new_state != file.selected {
+            file.set_selected(new_state);
+            changed += 1;
+        }
+    }
+    for sub in &mut dir.children_dirs {
+        changed += apply_pattern(sub, matcher, action);
+    }
+    changed
+}
@@ -0,0 +1,39 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{de::DeserializeOwned, Serialize};
+
+// Per-project data lives next to the code (the scanner skips dot-folders).
+// A .gitignore inside keeps it out of the project's repository.
+pub const PROJECT_DATA_DIR: &str = ".code_collector";
+const GITIGNORE: &str = "# Written by Code Collector\n*\n";
+
+pub fn data_path(project: &Path, file_name: &str) -> PathBuf {
+    project.join(PROJECT_DATA_DIR).join(file_name)
+}
+
+// Creates the data folder (and its .gitignore) before anything is written into it
+pub fn create_data_dir(project: &Path) -> io::Result<()> {
+    let dir = project.join(PROJECT_DATA_DIR);
+    fs::create_dir_all(&dir)?;
+    let gitignore = dir.join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(gitignore, GITIGNORE)?;
+    }
+    Ok(())
+}
+
+// Missing or unreadable files yield the default value
+pub fn load<T: DeserializeOwned + Default>(project: &Path, file_name: &str) -> T {
+    fs::read_to_string(data_path(project, file_name))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn save<T: Serialize>(project: &Path, file_name: &str, value: &T) -> io::Result<()> {
+    create_data_dir(project)?;
+    let path = data_path(project, file_name);
+    let text = serde_json::to_string_pretty(value).map_err(io::Error::other)?;
+    fs::write(path, text)
+}
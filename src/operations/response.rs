@@ -0,0 +1,465 @@
+use std::sync::OnceLock;
+use regex::Regex;
+use crate::models::dir_node::DirNode;
+use crate::models::line_range::LineRange;
+use crate::scanner::thread::is_ignored;
+use super::project_store;
+use super::selection::normalize_rel_path;
+use super::transform;
+
+// One file as written back by a model
+#[derive(Clone, Debug)]
+pub struct ResponseBlock {
+    pub path: String,
+    pub content: String,
+    pub partial: bool,  // Our banner says it is a diff or signatures only
+    pub numbered: bool, // Our banner says the lines carry a number gutter
+}
+
+// Where a parsed block lands in the project
+#[derive(Clone, Debug)]
+pub enum BlockTarget {
+    Existing(String), // rel_path of a scanned file
+    New(String),      // rel_path of a file to create
+    Invalid(String),  // Why the path cannot be used
+}
+
+// Finds file blocks in pasted text. Tries our own banner format first, then
+// XML documents, then Markdown code fences labelled with a path.
+pub fn parse_response(text: &str) -> Vec<ResponseBlock> {
+    let text = text.replace("\r\n", "\n");
+    for parser in [parse_banners, parse_xml, parse_markdown] {
+        let blocks = parser(&text);
+        if !blocks.is_empty() {
+            return blocks.into_iter()
+                .map(|b| ResponseBlock { content: clean_content(&b.content, b.numbered), ..b })
+                .collect();
+        }
+    }
+    Vec::new()
+}
+
+// =====
+// FILE: src/main.rs
+// LANGUAGE: rs
+// =====
+// A DIFF: or SKELETON: line in the banner marks a block that is not the whole file,
+// a NUMBERED: line one whose lines start with their line number.
+fn parse_banners(text: &str) -> Vec<ResponseBlock> {
+    static BANNER: OnceLock<Regex> = OnceLock::new();
+    let banner = BANNER.get_or_init(|| Regex::new(
+        r"(?m)^={10,}[ \t]*\nFILE:[ \t]*(.+?)[ \t]*\n((?:[A-Z]+:.*\n)*)={10,}[ \t]*\n"
+    ).unwrap());
+
+    let headers: Vec<(usize, usize, String, bool, bool)> = banner.captures_iter(text)
+        .map(|cap| {
+            let whole = cap.get(0).unwrap();
+            let partial = cap[2].lines().any(|l| l.starts_with("DIFF:") || l.starts_with("SKELETON:"));
+            let numbered = cap[2].lines().any(|l| l.starts_with("NUMBERED:"));
+            (whole.start(), whole.end(), cap[1].to_string(), partial, numbered)
+        })
+        .collect();
+    headers.iter().enumerate().map(|(i, (_, body_start, path, partial, numbered))| {
+        let content = match headers.get(i + 1) {
+            Some(next) => &text[*body_start..next.0],
+            None => without_closing_prose(&text[*body_start..], path),
+        };
+        ResponseBlock { path: path.clone(), content: content.to_string(), partial: *partial, numbered: *numbered }
+    }).collect()
+}
+
+// The last block runs to the end of the reply, where models like to sign off ("Let me know if…").
+// Fenced bodies end at their fence anyway; unfenced ones lose trailing paragraphs of sentences,
+// unless the file itself is prose.
+fn without_closing_prose<'a>(body: &'a str, path: &str) -> &'a str {
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+    if body.trim_start().starts_with("```") || body.trim_start().starts_with("~~~")
+        || matches!(extension.as_str(), "md" | "markdown" | "txt" | "rst" | "adoc" | "")
+    {
+        return body;
+    }
+    let mut end = body.trim_end().len();
+    while let Some(gap) = body[..end].rfind("\n\n") {
+        if !is_prose(&body[gap..end]) {
+            break;
+        }
+        end = body[..gap].trim_end().len();
+    }
+    &body[..end]
+}
+
+// Sentences: starts with a capital, ends with punctuation, and reads as words rather than code
+fn is_prose(paragraph: &str) -> bool {
+    let paragraph = paragraph.trim();
+    let words = paragraph.split_whitespace().count();
+    paragraph.chars().next().is_some_and(char::is_uppercase)
+        && paragraph.ends_with(['.', '!', '?', ':'])
+        && words >= 3
+        && !paragraph.contains(['{', '}', ';', '=', '(', '<'])
+}
+
+// <document path="src/main.rs">…</document>, <file path="…">…</file>, or
+// <document><source>src/main.rs</source><document_content>…</document_content></document>
+fn parse_xml(text: &str) -> Vec<ResponseBlock> {
+    static ELEMENT: OnceLock<Regex> = OnceLock::new();
+    static PATH_ATTR: OnceLock<Regex> = OnceLock::new();
+    static SOURCE: OnceLock<Regex> = OnceLock::new();
+    static CONTENT: OnceLock<Regex> = OnceLock::new();
+    let element = ELEMENT.get_or_init(|| Regex::new(r"(?s)<(document|file)\b([^>]*)>(.*?)</(?:document|file)>").unwrap());
+    let path_attr = PATH_ATTR.get_or_init(|| Regex::new(r#"\b(?:path|name|source)\s*=\s*["']([^"']+)["']"#).unwrap());
+    let source = SOURCE.get_or_init(|| Regex::new(r"(?s)<source>\s*(.*?)\s*</source>").unwrap());
+    let content = CONTENT.get_or_init(|| Regex::new(r"(?s)<(?:document_content|content)>(.*?)</(?:document_content|content)>").unwrap());
+
+    element.captures_iter(text).filter_map(|cap| {
+        let inner = &cap[3];
+        let path = path_attr.captures(&cap[2]).or_else(|| source.captures(inner))?[1].to_string();
+        let body = content.captures(inner).map(|c| c[1].to_string()).unwrap_or_else(|| inner.to_string());
+        Some(ResponseBlock { path: unescape_xml(&path), content: unescape_xml(&body), partial: false, numbered: false })
+    }).collect()
+}
+
+// ```rust src/main.rs   or   ### `src/main.rs` followed by a fence
+fn parse_markdown(text: &str) -> Vec<ResponseBlock> {
+    static FENCE: OnceLock<Regex> = OnceLock::new();
+    let fence = FENCE.get_or_init(|| Regex::new(r"^\s*(`{3,}|~{3,})\s*(.*)$").unwrap());
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(open) = fence.captures(lines[i]) else { i += 1; continue; };
+        let marker = open[1].to_string();
+        let path = path_in_info(&open[2]).or_else(|| {
+            // The label usually sits on the closest non-empty line above the fence
+            lines[..i].iter().rev().find(|l| !l.trim().is_empty()).and_then(|l| path_in_label(l))
+        });
+
+        let close = (i + 1..lines.len()).find(|&j| {
+            let trimmed = lines[j].trim();
+            trimmed.starts_with(&marker) && trimmed.chars().all(|c| c == marker.chars().next().unwrap_or('`'))
+        });
+        let end = close.unwrap_or(lines.len());
+        if let Some(path) = path {
+            let mut content = lines[i + 1..end].join("\n");
+            content.push('\n');
+            blocks.push(ResponseBlock { path, content, partial: false, numbered: false });
+        }
+        i = end + 1;
+    }
+    blocks
+}
+
+// "rust:src/main.rs", "rust title=src/main.rs", "src/main.rs"
+fn path_in_info(info: &str) -> Option<String> {
+    info.split(|c: char| c.is_whitespace() || c == ':' || c == '=')
+        .map(|token| token.trim_matches(|c| c == '"' || c == '\''))
+        .find(|token| looks_like_path(token))
+        .map(str::to_string)
+}
+
+// "### src/main.rs", "**`src/main.rs`**", "File: src/main.rs", "`src/main.rs`:"
+fn path_in_label(line: &str) -> Option<String> {
+    let label = line.trim()
+        .trim_start_matches(|c: char| c == '#' || c == '-' || c == '*' || c.is_whitespace());
+    let label = label.strip_prefix("File:").or_else(|| label.strip_prefix("FILE:")).unwrap_or(label);
+    let label = label.trim().trim_matches(|c: char| c == '*' || c == '`' || c == ':' || c == '"' || c.is_whitespace());
+    looks_like_path(label).then(|| label.to_string())
+}
+
+fn looks_like_path(token: &str) -> bool {
+    !token.is_empty()
+        && !token.contains(char::is_whitespace)
+        && (token.contains('/') || token.contains('\\') || token.rsplit_once('.').is_some_and(|(stem, ext)| !stem.is_empty() && !ext.is_empty()))
+}
+
+// Drops wrapping fences and blank edges, undoes exported line numbers, ends with one newline
+fn clean_content(content: &str, numbered: bool) -> String {
+    let mut lines: Vec<&str> = content.lines().collect();
+    while lines.first().is_some_and(|l| l.trim().is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    // A fenced body ends at its closing fence; anything after it is prose
+    let is_fence = |l: &str| l.trim_start().starts_with("```") || l.trim_start().starts_with("~~~");
+    let is_closing = |l: &str| { let t = l.trim(); t.len() >= 3 && t.chars().all(|c| c == '`' || c == '~') };
+    if lines.first().is_some_and(|l| is_fence(l)) {
+        if let Some(close) = lines.iter().skip(1).position(|l| is_closing(l)) {
+            lines = lines[1..close + 1].to_vec();
+        }
+    }
+
+    let lines = strip_line_numbers(&lines, numbered).unwrap_or_else(|| lines.iter().map(|l| l.to_string()).collect());
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+// Only when every non-empty line carries a number prefix, as in a numbered export.
+// Omission markers sit behind a blank gutter there. Without a NUMBERED: banner the
+// gutter must look exactly like ours: one separator, one width, and numbers that
+// count up by one except right after a marker. "200: OK" / "404: Not found" stays.
+fn strip_line_numbers(lines: &[&str], numbered: bool) -> Option<Vec<String>> {
+    static GUTTER: OnceLock<Regex> = OnceLock::new();
+    let gutter = GUTTER.get_or_init(|| Regex::new(r"^( *(\d+))( \| ?|: ?|\t)").unwrap());
+    let marker = omission_marker();
+
+    let mut previous: Option<usize> = None;
+    let mut after_marker = true;
+    let mut shape: Option<(usize, &str)> = None; // (gutter width, separator)
+    let mut any_numbered = false;
+    for line in lines.iter().filter(|l| !l.trim().is_empty()) {
+        if marker.is_match(line) {
+            after_marker = true;
+            continue;
+        }
+        let cap = gutter.captures(line)?;
+        let number: usize = cap[2].parse().ok()?;
+        let this_shape = (cap[1].len(), cap.get(3).unwrap().as_str().trim_end_matches(' '));
+        let in_order = match previous {
+            None => true,
+            Some(prev) if numbered => number > prev,
+            Some(prev) => number == prev + 1 || (after_marker && number > prev),
+        };
+        if !in_order || (!numbered && shape.is_some_and(|s| s != this_shape)) {
+            return None;
+        }
+        shape = Some(this_shape);
+        previous = Some(number);
+        after_marker = false;
+        any_numbered = true;
+    }
+    if !any_numbered {
+        return None;
+    }
+    Some(lines.iter().map(|l| match marker.captures(l) {
+        Some(cap) => cap[1].to_string(),
+        None => gutter.replace(l, "").to_string(),
+    }).collect())
+}
+
+// "[… 12 lines omitted …]" or "[lines 3-9]", possibly behind a line-number gutter
+fn omission_marker() -> &'static Regex {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    MARKER.get_or_init(|| Regex::new(r"(?m)^\s*[|:]?\s*(\[(?:… \d+ lines omitted …|lines (\d+)-(\d+))\])\s*$").unwrap())
+}
+
+// Markers our exports put in place of code, and elided skeleton bodies. A block containing them is not a whole file.
+pub fn looks_partial(content: &str) -> bool {
+    static ELIDED_BODY: OnceLock<Regex> = OnceLock::new();
+    let elided_body = ELIDED_BODY.get_or_init(|| Regex::new(r"(?m)\{ \.\.\. \}\s*$").unwrap());
+    omission_marker().is_match(content) || elided_body.is_match(content)
+}
+
+// The line ranges of an export made from a partial selection ("[lines a-b]" markers)
+pub fn exported_ranges(content: &str) -> Vec<LineRange> {
+    omission_marker().captures_iter(content)
+        .filter_map(|cap| Some(LineRange::new(cap.get(2)?.as_str().parse().ok()?, cap.get(3)?.as_str().parse().ok()?)))
+        .collect()
+}
+
+// Model output always comes back with "\n"; files that use "\r\n" keep it
+pub fn match_line_endings(content: &str, existing: &str) -> String {
+    match transform::line_ending(existing) {
+        "\r\n" => content.replace("\r\n", "\n").replace('\n', "\r\n"),
+        _ => content.to_string(),
+    }
+}
+
+// Exact rel_path first, then a unique path suffix ("main.rs" for "src/main.rs").
+// Paths that would escape the project are refused, and so are folders the scanner
+// skips (.git, .code_collector, target, node_modules, …): a reply must not be able
+// to plant git hooks or overwrite app data.
+pub fn resolve_target(root: &DirNode, path: &str) -> BlockTarget {
+    let path = normalize_rel_path(path.trim());
+    let path = path.trim_start_matches("./").to_string();
+    if path.is_empty() || path.starts_with('/') || path.contains(':') || path.split('/').any(|part| part == "..") {
+        return BlockTarget::Invalid(format!("Unsafe path: {}", path));
+    }
+    let mut folders = path.split('/').rev().skip(1);
+    if let Some(folder) = folders.find(|part| is_ignored(part) || *part == project_store::PROJECT_DATA_DIR) {
+        return BlockTarget::Invalid(format!("Protected folder {}: {}", folder, path));
+    }
+
+    let mut all = Vec::new();
+    collect_rel_paths(root, &mut all);
+    if all.contains(&path) {
+        return BlockTarget::Existing(path);
+    }
+    let suffix = format!("/{}", path);
+    let mut matches = all.iter().filter(|p| p.ends_with(&suffix));
+    match (matches.next(), matches.next()) {
+        (Some(only), None) => BlockTarget::Existing(only.clone()),
+        (Some(_), Some(_)) => BlockTarget::Invalid(format!("Ambiguous path: {}", path)),
+        _ => BlockTarget::New(path),
+    }
+}
+
+fn collect_rel_paths(dir: &DirNode, out: &mut Vec<String>) {
+    for file in &dir.children_files {
+        out.push(normalize_rel_path(&file.rel_path));
+    }
+    for sub in &dir.children_dirs {
+        collect_rel_paths(sub, out);
+    }
+}
+
+fn unescape_xml(text: &str) -> String {
+    if let Some(cdata) = text.trim().strip_prefix("<![CDATA[").and_then(|t| t.strip_suffix("]]>")) {
+        return cdata.to_string();
+    }
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::models::file_node::FileNode;
+    use super::*;
+
+    fn project(rel_paths: &[&str]) -> DirNode {
+        let files = rel_paths.iter().map(|rel_path| FileNode {
+            name: rel_path.rsplit('/').next().unwrap_or_default().to_string(),
+            path: PathBuf::from(rel_path),
+            rel_path: rel_path.to_string(),
+            extension: rel_path.rsplit('.').next().unwrap_or_default().to_string(),
+            selected: false,
+            size_bytes: 0,
+            line_count: None,
+            skeleton: false,
+            line_ranges: Vec::new(),
+            git_status: None,
+            blob: None,
+        }).collect();
+        DirNode { name: String::new(), path: PathBuf::new(), children_dirs: Vec::new(), children_files: files }
+    }
+
+    fn parsed(text: &str) -> Vec<(String, String)> {
+        parse_response(text).into_iter().map(|b| (b.path, b.content)).collect()
+    }
+
+    #[test]
+    fn banners() {
+        let bar = "=".repeat(50);
+        let text = format!("\n\n{bar}\nFILE: src/a.rs\nLANGUAGE: rs\n{bar}\n\nfn a() {{}}\n\n\n{bar}\nFILE: b.py\nLANGUAGE: py\n{bar}\n\nprint(1)\n");
+        assert_eq!(parsed(&text), vec![
+            ("src/a.rs".to_string(), "fn a() {}\n".to_string()),
+            ("b.py".to_string(), "print(1)\n".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn banner_blocks_end_before_closing_prose() {
+        let bar = "=".repeat(50);
+        let text = format!("{bar}\nFILE: src/a.rs\nLANGUAGE: rs\n{bar}\n\nfn a() {{}}\n\nLet me know if you want the tests updated too.\n\nCheers, hope this helps!\n");
+        assert_eq!(parsed(&text), vec![("src/a.rs".to_string(), "fn a() {}\n".to_string())]);
+
+        // Prose files keep their last paragraph
+        let text = format!("{bar}\nFILE: README.md\nLANGUAGE: md\n{bar}\n\n# Title\n\nRun the app with cargo run.\n");
+        assert_eq!(parsed(&text)[0].1, "# Title\n\nRun the app with cargo run.\n");
+    }
+
+    #[test]
+    fn xml_documents() {
+        let text = "<document path=\"src/a.rs\">\nif a &lt; b {}\n</document>\n\
+            <document><source>b.rs</source><document_content><![CDATA[x < y]]></document_content></document>";
+        assert_eq!(parsed(text), vec![
+            ("src/a.rs".to_string(), "if a < b {}\n".to_string()),
+            ("b.rs".to_string(), "x < y\n".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn markdown_fences_with_prose_around() {
+        let text = "Here is the fix:\n\n### `src/a.rs`\n```rust\nfn a() {}\n```\n\nThat should do it.\n\n```ts web/b.ts\nlet b = 1;\n```\nDone.\n";
+        assert_eq!(parsed(text), vec![
+            ("src/a.rs".to_string(), "fn a() {}\n".to_string()),
+            ("web/b.ts".to_string(), "let b = 1;\n".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn fences_without_a_path_are_ignored() {
+        assert!(parse_response("Run this:\n```\ncargo build\n```\n").is_empty());
+    }
+
+    #[test]
+    fn crlf_input_and_line_numbers() {
+        let text = "```rust src/a.rs\r\n 1 | fn a() {\r\n 2 |     b();\r\n 3 | }\r\n```\r\n";
+        assert_eq!(parsed(text), vec![("src/a.rs".to_string(), "fn a() {\n    b();\n}\n".to_string())]);
+    }
+
+    #[test]
+    fn number_like_content_survives() {
+        let yaml = "```yaml config/status.yml\n200: OK\n404: Not found\n500: Server error\n```\n";
+        assert_eq!(parsed(yaml), vec![("config/status.yml".to_string(), "200: OK\n404: Not found\n500: Server error\n".to_string())]);
+        let tsv = "```tsv data/ids.tsv\n7\tseven\n8\teight\n10\tten\n```\n";
+        assert_eq!(parsed(tsv)[0].1, "7\tseven\n8\teight\n10\tten\n");
+    }
+
+    #[test]
+    fn numbered_banner_allows_gaps() {
+        // Stripped comments leave gaps in the numbering
+        let bar = "=".repeat(50);
+        let text = format!("{bar}\nFILE: a.rs\nLANGUAGE: rs\nNUMBERED: line numbers are not part of the file\n{bar}\n\n 2 | fn a() {{\n 5 |     b();\n12 | }}\n");
+        assert_eq!(parsed(&text), vec![("a.rs".to_string(), "fn a() {\n    b();\n}\n".to_string())]);
+    }
+
+    #[test]
+    fn line_endings_follow_the_existing_file() {
+        assert_eq!(match_line_endings("a\nb\n", "x\r\ny\r\n"), "a\r\nb\r\n");
+        assert_eq!(match_line_endings("a\nb\n", "x\ny\n"), "a\nb\n");
+    }
+
+    #[test]
+    fn omission_markers_are_partial() {
+        assert!(looks_partial("fn a() {\n[… 12 lines omitted …]\n}\n"));
+        assert!(looks_partial("[lines 3-9]\nlet a = 1;\n"));
+        assert!(!looks_partial("let a = [1, 2];\n"));
+    }
+
+    #[test]
+    fn diff_and_skeleton_banners_are_partial() {
+        let bar = "=".repeat(50);
+        let text = format!("{bar}\nFILE: a.rs\nLANGUAGE: rs\nDIFF: main -> working tree\n{bar}\n\n@@ -1 +1 @@\n-a\n+b\n\n\
+            {bar}\nFILE: b.rs\nLANGUAGE: rs\nSKELETON: signatures only\n{bar}\n\nfn b() {{ ... }}\n\n\
+            {bar}\nFILE: c.rs\nLANGUAGE: rs\n{bar}\n\nfn c() {{}}\n");
+        let partial: Vec<bool> = parse_response(&text).iter().map(|b| b.partial).collect();
+        assert_eq!(partial, vec![true, true, false]);
+        assert!(looks_partial("fn b() { ... }\n"));
+    }
+
+    #[test]
+    fn ranges_from_markers() {
+        let plain = "[lines 3-5]\nlet a = 1;\n[lines 10-12]\nlet b = 2;\n";
+        assert_eq!(exported_ranges(plain), vec![LineRange::new(3, 5), LineRange::new(10, 12)]);
+
+        // Numbered exports put the markers behind a blank gutter
+        let blocks = parse_response("```rust src/a.rs\n   | [lines 8-9]\n 8 | let a = 1;\n 9 | let b = 2;\n```\n");
+        assert_eq!(blocks[0].content, "[lines 8-9]\nlet a = 1;\nlet b = 2;\n");
+        assert_eq!(exported_ranges(&blocks[0].content), vec![LineRange::new(8, 9)]);
+    }
+
+    #[test]
+    fn targets() {
+        let root = project(&["src/main.rs", "src/ui/mod.rs", "tests/mod.rs"]);
+        assert!(matches!(resolve_target(&root, "src/main.rs"), BlockTarget::Existing(p) if p == "src/main.rs"));
+        assert!(matches!(resolve_target(&root, "./src/main.rs"), BlockTarget::Existing(p) if p == "src/main.rs"));
+        assert!(matches!(resolve_target(&root, "main.rs"), BlockTarget::Existing(p) if p == "src/main.rs"));
+        assert!(matches!(resolve_target(&root, "ui/mod.rs"), BlockTarget::Existing(p) if p == "src/ui/mod.rs"));
+        assert!(matches!(resolve_target(&root, "mod.rs"), BlockTarget::Invalid(_)));
+        assert!(matches!(resolve_target(&root, "src/new.rs"), BlockTarget::New(p) if p == "src/new.rs"));
+        assert!(matches!(resolve_target(&root, ".env"), BlockTarget::New(p) if p == ".env"));
+    }
+
+    #[test]
+    fn unsafe_targets() {
+        let root = project(&["src/main.rs"]);
+        for path in ["../outside.rs", "src/../../x.rs", "/etc/passwd", "C:\\x.rs", "",
+            ".git/hooks/pre-commit", ".code_collector/history.json", "target/debug/app", "web/node_modules/x/index.js"]
+        {
+            assert!(matches!(resolve_target(&root, path), BlockTarget::Invalid(_)), "{}", path);
+        }
+    }
+}
@@ -1,16 +1,343 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use regex::{Regex, RegexBuilder};
 use crate::models::dir_node::DirNode;
+use crate::models::file_node::FileNode;
+use super::{content, selection};
 
-pub fn matches_search(dir: &DirNode, query: &str) -> bool {
-    if query.is_empty() { return true; }
-    let q = query.to_lowercase();
-    
+// --- Fuzzy path search ---
+
+const SCORE_MATCH: i32 = 16;
+const BONUS_SEGMENT: i32 = 10;   // First char of a path segment
+const BONUS_WORD: i32 = 8;       // After '_', '-', '.' or ' '
+const BONUS_CAMEL: i32 = 7;      // fooBar: the 'B'
+const BONUS_CONSECUTIVE: i32 = 8;
+const BONUS_FILE_NAME: i32 = 6;  // Matches in the file name beat matches in folders
+const PENALTY_GAP_START: i32 = 3;
+const PENALTY_GAP_EXTEND: i32 = 1;
+const MIN_RUN: usize = 3;        // Consecutive chars that make a match worth keeping on their own
+
+#[derive(Clone, Debug)]
+pub struct FuzzyMatch {
+    pub score: i32,
+    pub positions: Vec<usize>, // Char indices into the rel_path
+}
+
+// Normalized rel_path -> match; files that are not in the map are filtered out
+pub type SearchMatches = HashMap<String, FuzzyMatch>;
+
+// Every query char must appear in order (case-insensitive); the best-scoring alignment wins
+pub fn fuzzy_match(query: &str, rel_path: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some(FuzzyMatch { score: 0, positions: Vec::new() });
+    }
+    let path: Vec<char> = rel_path.chars().collect();
+    let lower: Vec<char> = path.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let (m, n) = (query.len(), path.len());
+    if m > n {
+        return None;
+    }
+
+    let name_start = path.iter().rposition(|&c| c == '/').map(|i| i + 1).unwrap_or(0);
+    let bonus: Vec<i32> = (0..n).map(|j| {
+        let mut bonus = if j >= name_start { BONUS_FILE_NAME } else { 0 };
+        bonus += match j.checked_sub(1).map(|k| path[k]) {
+            None | Some('/') => BONUS_SEGMENT,
+            Some('_' | '-' | '.' | ' ') => BONUS_WORD,
+            Some(prev) if prev.is_lowercase() && path[j].is_uppercase() => BONUS_CAMEL,
+            _ => 0,
+        };
+        bonus
+    }).collect();
+
+    // score[i][j]: best score with query[i] matched at path[j]; from[i][j]: where query[i - 1] was
+    const NONE: i32 = i32::MIN / 2;
+    let mut score = vec![vec![NONE; n]; m];
+    let mut from = vec![vec![0usize; n]; m];
+    for j in 0..n {
+        if lower[j] == query[0] {
+            score[0][j] = SCORE_MATCH + bonus[j];
+        }
+    }
+    for i in 1..m {
+        // Best earlier match that leaves a gap, kept as score + k * extend so the penalty can be added later
+        let mut best_gap: Option<(i32, usize)> = None;
+        for j in i..n {
+            if j >= 2 && score[i - 1][j - 2] > NONE {
+                let candidate = score[i - 1][j - 2] + (j - 2) as i32 * PENALTY_GAP_EXTEND;
+                if best_gap.is_none_or(|(best, _)| candidate > best) {
+                    best_gap = Some((candidate, j - 2));
+                }
+            }
+            if lower[j] != query[i] {
+                continue;
+            }
+            let adjacent = (score[i - 1][j - 1] > NONE).then(|| (score[i - 1][j - 1] + BONUS_CONSECUTIVE, j - 1));
+            let gapped = best_gap.map(|(value, k)| (value - (j - 1) as i32 * PENALTY_GAP_EXTEND - PENALTY_GAP_START, k));
+            let best = match (adjacent, gapped) {
+                (Some(a), Some(g)) => if a.0 >= g.0 { a } else { g },
+                (Some(a), None) => a,
+                (None, Some(g)) => g,
+                (None, None) => continue,
+            };
+            score[i][j] = best.0 + SCORE_MATCH + bonus[j];
+            from[i][j] = best.1;
+        }
+    }
+
+    let (mut j, best) = score[m - 1].iter().enumerate().max_by_key(|(_, s)| **s).map(|(j, s)| (j, *s))?;
+    if best <= NONE {
+        return None;
+    }
+    let mut positions = vec![0; m];
+    for i in (0..m).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+    // "export" should rank export.rs above export_preview.rs
+    let stem_end = path[name_start..].iter().rposition(|&c| c == '.').filter(|&i| i > 0).map(|i| name_start + i).unwrap_or(n);
+    let whole_stem = positions.contains(&name_start) && positions.last() == Some(&(stem_end - 1));
+    let best = if whole_stem { best + BONUS_SEGMENT } else { best };
+    // Shorter paths win ties
+    Some(FuzzyMatch { score: best - (n / 8) as i32, positions })
+}
+
+// Scattered single-char hits match almost any long path; keep a match only if it scores at least
+// a file-name match per query char, has a run of consecutive chars, or lands every jump on a segment or word start
+fn is_relevant(found: &FuzzyMatch, rel_path: &str) -> bool {
+    let m = found.positions.len();
+    if found.score >= m as i32 * (SCORE_MATCH + BONUS_FILE_NAME) {
+        return true;
+    }
+    let path: Vec<char> = rel_path.chars().collect();
+    let mut run = 0;
+    let mut on_boundaries = true;
+    for (i, &pos) in found.positions.iter().enumerate() {
+        if i > 0 && pos == found.positions[i - 1] + 1 {
+            run += 1;
+        } else {
+            run = 1;
+            on_boundaries &= match pos.checked_sub(1).map(|k| path[k]) {
+                None | Some('/' | '_' | '-' | '.' | ' ') => true,
+                Some(prev) => prev.is_lowercase() && path[pos].is_uppercase(),
+            };
+        }
+        if run >= MIN_RUN.min(m) {
+            return true;
+        }
+    }
+    on_boundaries
+}
+
+pub fn match_tree(root: &DirNode, query: &str) -> SearchMatches {
+    let mut matches = HashMap::new();
+    match_dir(root, query, &mut matches);
+    matches
+}
+
+fn match_dir(dir: &DirNode, query: &str, matches: &mut SearchMatches) {
     for file in &dir.children_files {
-        if file.name.to_lowercase().contains(&q) { return true; }
+        let rel_path = selection::normalize_rel_path(&file.rel_path);
+        if let Some(found) = fuzzy_match(query, &rel_path).filter(|found| is_relevant(found, &rel_path)) {
+            matches.insert(rel_path, found);
+        }
+    }
+    for sub in &dir.children_dirs {
+        match_dir(sub, query, matches);
+    }
+}
+
+// None = no active search, everything is shown
+pub fn is_visible(file: &FileNode, matches: Option<&SearchMatches>) -> bool {
+    matches.is_none_or(|m| m.contains_key(&selection::normalize_rel_path(&file.rel_path)))
+}
+
+pub fn dir_has_match(dir: &DirNode, matches: Option<&SearchMatches>) -> bool {
+    matches.is_none()
+        || dir.children_files.iter().any(|f| is_visible(f, matches))
+        || dir.children_dirs.iter().any(|d| dir_has_match(d, matches))
+}
+
+// --- Content search ---
+
+const MAX_HITS: usize = 5000;
+const MAX_SNIPPET_BYTES: usize = 200;
+
+#[derive(Clone, Debug, Default)]
+pub struct ContentQuery {
+    pub text: String,
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+}
+
+impl ContentQuery {
+    pub fn compile(&self) -> Result<Regex, String> {
+        if self.text.is_empty() {
+            return Err("Empty query".to_string());
+        }
+        let mut pattern = if self.regex { self.text.clone() } else { regex::escape(&self.text) };
+        if self.whole_word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|e| e.to_string())
     }
+}
 
+#[derive(Clone, Debug)]
+pub struct SearchHit {
+    pub rel_path: String,
+    pub line: usize,                    // 1-based
+    pub snippet: String,                // The matching line, shortened around the match
+    pub columns: (usize, usize),        // Byte span of the match inside `snippet`
+}
+
+pub enum SearchMessage {
+    Hits(Vec<SearchHit>), // All hits of one file
+    Finished { scanned: usize, truncated: bool },
+}
+
+// Searches the files line by line; binary files are skipped. Runs on a background thread.
+pub fn search_files(files: &[FileNode], regex: &Regex, tx: &Sender<SearchMessage>, cancel: &AtomicBool) {
+    let mut total = 0;
+    let mut scanned = 0;
+    let mut truncated = false;
+    content::for_each_file(files, cancel, |file, bytes| {
+        if truncated || bytes.iter().take(8000).any(|&b| b == 0) {
+            return;
+        }
+        scanned += 1;
+        let text = String::from_utf8_lossy(&bytes);
+        let mut hits = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            if let Some(found) = regex.find(line) {
+                hits.push(snippet_hit(&file.rel_path, idx + 1, line, found.start(), found.end()));
+            }
+        }
+        if hits.is_empty() {
+            return;
+        }
+        total += hits.len();
+        truncated = total >= MAX_HITS;
+        if truncated {
+            hits.truncate(hits.len() - (total - MAX_HITS));
+            // Nothing else will be shown, so stop reading
+            cancel.store(true, Ordering::Relaxed);
+        }
+        if tx.send(SearchMessage::Hits(hits)).is_err() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    });
+    if truncated || !cancel.load(Ordering::Relaxed) {
+        let _ = tx.send(SearchMessage::Finished { scanned, truncated });
+    }
+}
+
+fn snippet_hit(rel_path: &str, line: usize, text: &str, start: usize, end: usize) -> SearchHit {
+    let trimmed = text.trim_start();
+    let offset = text.len() - trimmed.len();
+    let (mut start, mut end) = (start.saturating_sub(offset), end.saturating_sub(offset));
+    let mut snippet = trimmed.trim_end().to_string();
+
+    // Long lines (minified code) keep some context before the match
+    if snippet.len() > MAX_SNIPPET_BYTES {
+        let mut from = start.saturating_sub(40);
+        while !snippet.is_char_boundary(from) {
+            from -= 1;
+        }
+        let mut to = end.max(from + MAX_SNIPPET_BYTES).min(snippet.len());
+        while !snippet.is_char_boundary(to) {
+            to += 1;
+        }
+        let prefix = if from > 0 { "…" } else { "" };
+        let suffix = if to < snippet.len() { "…" } else { "" };
+        start = start - from + prefix.len();
+        end = end - from + prefix.len();
+        snippet = format!("{}{}{}", prefix, &snippet[from..to], suffix);
+    }
+    end = end.min(snippet.len());
+    start = start.min(end);
+    SearchHit { rel_path: selection::normalize_rel_path(rel_path), line, snippet, columns: (start, end) }
+}
+
+// Every file in the tree, in tree order
+pub fn collect_files(dir: &DirNode, files: &mut Vec<FileNode>) {
+    files.extend(dir.children_files.iter().cloned());
     for sub in &dir.children_dirs {
-        if matches_search(sub, query) { return true; }
+        collect_files(sub, files);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked<'a>(query: &str, paths: &[&'a str]) -> Vec<&'a str> {
+        let mut found: Vec<(i32, &str)> = paths.iter()
+            .filter_map(|path| fuzzy_match(query, path).map(|m| (m.score, *path)))
+            .collect();
+        found.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+        found.into_iter().map(|(_, path)| path).collect()
+    }
+
+    #[test]
+    fn whole_file_stem_ranks_first() {
+        let paths = ["src/ui/export_preview.rs", "src/operations/export.rs", "src/models/export_record.rs"];
+        assert_eq!(ranked("export", &paths)[0], "src/operations/export.rs");
+    }
+
+    #[test]
+    fn file_name_beats_folder() {
+        let paths = ["src/app/state.rs", "src/ui/app.rs"];
+        assert_eq!(ranked("app", &paths), vec!["src/ui/app.rs", "src/app/state.rs"]);
+    }
+
+    #[test]
+    fn segment_starts_beat_scattered_chars() {
+        let paths = ["src/ui/tree_view_helpers.rs", "src/models/file_node.rs"];
+        assert_eq!(ranked("fn", &paths)[0], "src/models/file_node.rs");
+    }
+
+    #[test]
+    fn shorter_path_wins_ties() {
+        let paths = ["deeply/nested/folder/main.rs", "src/main.rs"];
+        assert_eq!(ranked("main", &paths), vec!["src/main.rs", "deeply/nested/folder/main.rs"]);
+    }
+
+    #[test]
+    fn positions_point_at_matched_chars() {
+        let found = fuzzy_match("fnode", "src/models/file_node.rs").unwrap();
+        assert_eq!(found.positions, vec![11, 16, 17, 18, 19]);
+        let found = fuzzy_match("Main", "src/main.rs").unwrap();
+        assert_eq!(found.positions, vec![4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn positions_are_char_indices() {
+        let found = fuzzy_match("ü", "docs/über.md").unwrap();
+        assert_eq!(found.positions, vec![5]);
+    }
+
+    #[test]
+    fn chars_must_appear_in_order() {
+        assert!(fuzzy_match("sm", "src/main.rs").is_some());
+        assert!(fuzzy_match("ms", "main").is_none());
+        assert!(fuzzy_match("toolong", "a.rs").is_none());
+    }
+
+    #[test]
+    fn scattered_matches_are_dropped() {
+        let scattered = "src/operations/transform.rs";
+        let found = fuzzy_match("sotr", scattered).unwrap();
+        assert!(is_relevant(&found, scattered));
+        let found = fuzzy_match("ptnm", scattered).unwrap();
+        assert!(!is_relevant(&found, scattered));
+        let found = fuzzy_match("trans", scattered).unwrap();
+        assert!(is_relevant(&found, scattered));
     }
-    
-    false
-}
\ No newline at end of file
+}
@@ -1,17 +1,19 @@
-use std::collections::HashSet;
-use std::path::PathBuf;
-use crate::models::dir_node::DirNode;
-
-pub fn set_dir_selection(dir: &mut DirNode, state: bool, query: &str) {
-    let is_visible = |name: &str| query.is_empty() || name.to_lowercase().contains(&query.to_lowercase());
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use crate::models::{dir_node::DirNode, file_node::FileNode, line_range::{self, LineRange}};
+use crate::models::profile::{ApplyMode, SelectionEntry};
+use super::{content, search};
+use super::search::SearchMatches;
 
+// Only files shown by the current search are touched
+pub fn set_dir_selection(dir: &mut DirNode, state: bool, matches: Option<&SearchMatches>) {
     for file in &mut dir.children_files {
-        if is_visible(&file.name) {
-            file.selected = state;
+        if search::is_visible(file, matches) {
+            file.set_selected(state);
         }
     }
     for sub in &mut dir.children_dirs {
-        set_dir_selection(sub, state, query);
+        set_dir_selection(sub, state, matches);
     }
 }
 
@@ -26,10 +28,10 @@ pub fn is_dir_fully_selected(dir: &DirNode) -> bool {
     true
 }
 
-pub fn collect_selected_paths(dir: &DirNode, set: &mut HashSet<PathBuf>) {
+pub fn collect_selected_paths(dir: &DirNode, set: &mut HashMap<PathBuf, Vec<LineRange>>) {
     for file in &dir.children_files {
         if file.selected {
-            set.insert(file.path.clone());
+            set.insert(file.path.clone(), file.line_ranges.clone());
         }
     }
     for sub in &dir.children_dirs {
@@ -37,10 +39,18 @@ pub fn collect_selected_paths(dir: &DirNode, set: &mut HashSet<PathBuf>) {
     }
 }
 
-pub fn restore_selections(dir: &mut DirNode, set: &HashSet<PathBuf>) {
+pub fn collect_selected_files(dir: &DirNode, files: &mut Vec<FileNode>) {
+    files.extend(dir.children_files.iter().filter(|f| f.selected).cloned());
+    for sub in &dir.children_dirs {
+        collect_selected_files(sub, files);
+    }
+}
+
+pub fn restore_selections(dir: &mut DirNode, set: &HashMap<PathBuf, Vec<LineRange>>) {
     for file in &mut dir.children_files {
-        if set.contains(&file.path) {
+        if let Some(ranges) = set.get(&file.path) {
             file.selected = true;
+            file.line_ranges = ranges.clone();
         }
     }
     for sub in &mut dir.children_dirs {
@@ -48,10 +58,74 @@ pub fn restore_selections(dir: &mut DirNode, set: &HashSet<PathBuf>) {
     }
 }
 
+pub fn normalize_rel_path(rel_path: &str) -> String {
+    rel_path.replace('\\', "/")
+}
+
+// Project-relative form of the selection, for profiles and sessions
+pub fn collect_selected_entries(dir: &DirNode, entries: &mut Vec<SelectionEntry>) {
+    for file in &dir.children_files {
+        if file.selected {
+            entries.push(SelectionEntry {
+                rel_path: normalize_rel_path(&file.rel_path),
+                ranges: file.line_ranges.clone(),
+            });
+        }
+    }
+    for sub in &dir.children_dirs {
+        collect_selected_entries(sub, entries);
+    }
+}
+
+// Applies saved entries and returns the ones whose files no longer exist
+pub fn apply_entries(root: &mut DirNode, entries: &[SelectionEntry], mode: ApplyMode) -> Vec<String> {
+    if mode == ApplyMode::Replace {
+        clear_selection(root);
+    }
+    let wanted: HashMap<&str, &SelectionEntry> = entries.iter().map(|e| (e.rel_path.as_str(), e)).collect();
+    let mut found = HashSet::new();
+    apply_entries_recursive(root, &wanted, &mut found);
+
+    entries.iter()
+        .filter(|e| !found.contains(&e.rel_path))
+        .map(|e| e.rel_path.clone())
+        .collect()
+}
+
+fn apply_entries_recursive(dir: &mut DirNode, wanted: &HashMap<&str, &SelectionEntry>, found: &mut HashSet<String>) {
+    for file in &mut dir.children_files {
+        let rel_path = normalize_rel_path(&file.rel_path);
+        if let Some(entry) = wanted.get(rel_path.as_str()) {
+            let was_whole = file.selected && file.line_ranges.is_empty();
+            if entry.ranges.is_empty() || !file.selected {
+                file.line_ranges = entry.ranges.clone();
+            } else if !was_whole {
+                for range in &entry.ranges {
+                    line_range::add_range(&mut file.line_ranges, *range);
+                }
+            }
+            file.selected = true;
+            found.insert(rel_path);
+        }
+    }
+    for sub in &mut dir.children_dirs {
+        apply_entries_recursive(sub, wanted, found);
+    }
+}
+
+pub fn clear_selection(dir: &mut DirNode) {
+    for file in &mut dir.children_files {
+        file.set_selected(false);
+    }
+    for sub in &mut dir.children_dirs {
+        clear_selection(sub);
+    }
+}
+
 pub fn validate_selections(dir: &DirNode) -> bool {
     // Returns false if any selected file no longer exists on disk
     for file in &dir.children_files {
-        if file.selected && !file.path.exists() {
+        if file.selected && !content::exists(file) {
             return false;
         }
     }
@@ -61,4 +135,18 @@ pub fn validate_selections(dir: &DirNode) -> bool {
         }
     }
     true
-}
\ No newline at end of file
+}
+
+pub fn find_file_by_rel_path<'a>(dir: &'a DirNode, rel_path: &str) -> Option<&'a FileNode> {
+    if let Some(file) = dir.children_files.iter().find(|f| normalize_rel_path(&f.rel_path) == rel_path) {
+        return Some(file);
+    }
+    dir.children_dirs.iter().find_map(|sub| find_file_by_rel_path(sub, rel_path))
+}
+
+pub fn find_file_mut<'a>(dir: &'a mut DirNode, path: &Path) -> Option<&'a mut FileNode> {
+    if let Some(pos) = dir.children_files.iter().position(|f| f.path == path) {
+        return dir.children_files.get_mut(pos);
+    }
+    dir.children_dirs.iter_mut().find_map(|sub| find_file_mut(sub, path))
+}
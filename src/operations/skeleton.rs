@@ -0,0 +1,223 @@
+use tree_sitter::{Language, Node, Parser};
+use super::transform::SourceLine;
+
+// Nodes whose body gets elided. Kinds that do not exist in a grammar simply never match.
+const FUNCTION_KINDS: &[&str] = &[
+    "function_item",                                            // Rust
+    "function_declaration", "function_expression", "generator_function_declaration",
+    "generator_function", "method_definition", "arrow_function", // JS / TS
+    "function_definition",                                      // Python, C, C++, PHP
+    "method_declaration", "constructor_declaration",
+    "compact_constructor_declaration",                          // Java, Go, PHP
+    "func_literal",                                             // Go
+    "lambda_expression",                                        // C++
+    "anonymous_function",                                       // PHP
+];
+
+// Only statement blocks are elided; expression bodies (`x => x + 1`) are already short
+const BLOCK_KINDS: &[&str] = &["block", "statement_block", "compound_statement", "constructor_body"];
+
+const BRACE_PLACEHOLDER: &str = "{ ... }";
+const PYTHON_PLACEHOLDER: &str = "...";
+
+fn language_for(extension: &str) -> Option<Language> {
+    let language = match extension {
+        "rs" => tree_sitter_rust::LANGUAGE,
+        "js" | "mjs" | "cjs" | "jsx" => tree_sitter_javascript::LANGUAGE,
+        "ts" | "mts" | "cts" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+        "tsx" => tree_sitter_typescript::LANGUAGE_TSX,
+        "py" | "pyw" => tree_sitter_python::LANGUAGE,
+        "java" => tree_sitter_java::LANGUAGE,
+        "go" => tree_sitter_go::LANGUAGE,
+        "c" | "h" => tree_sitter_c::LANGUAGE,
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => tree_sitter_cpp::LANGUAGE,
+        "php" => tree_sitter_php::LANGUAGE_PHP,
+        _ => return None,
+    };
+    Some(language.into())
+}
+
+pub fn supports(extension: &str) -> bool {
+    language_for(extension).is_some()
+}
+
+// Keeps imports, type declarations, signatures and doc comments, and replaces
+// function bodies with a placeholder. Returns None for unsupported languages.
+pub fn skeletonize(lines: &[SourceLine], extension: &str) -> Option<Vec<SourceLine>> {
+    let language = language_for(extension)?;
+    if lines.is_empty() {
+        return None;
+    }
+
+    let code = lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("\n");
+    let mut parser = Parser::new();
+    parser.set_language(&language).ok()?;
+    let tree = parser.parse(&code, None)?;
+
+    let mut elisions = Vec::new();
+    collect_elisions(tree.root_node(), &code, extension, &mut elisions);
+    Some(splice(lines, &code, &elisions))
+}
+
+fn collect_elisions(node: Node, code: &str, extension: &str, elisions: &mut Vec<(usize, usize, String)>) {
+    if FUNCTION_KINDS.contains(&node.kind()) {
+        if let Some(body) = node.child_by_field_name("body").filter(|b| BLOCK_KINDS.contains(&b.kind())) {
+            elisions.push(if extension.starts_with("py") {
+                python_elision(body, code)
+            } else {
+                (body.start_byte(), body.end_byte(), BRACE_PLACEHOLDER.to_string())
+            });
+            return;
+        }
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_elisions(child, code, extension, elisions);
+    }
+}
+
+// Python blocks start at the first statement; a leading docstring is kept
+fn python_elision(body: Node, code: &str) -> (usize, usize, String) {
+    let docstring = body.named_child(0).filter(|first| {
+        first.kind() == "expression_statement" && first.named_child(0).is_some_and(|n| n.kind() == "string")
+    });
+
+    match docstring {
+        Some(doc) => {
+            let line_start = code[..body.start_byte()].rfind('\n').map(|p| p + 1).unwrap_or(0);
+            let indent = &code[line_start..body.start_byte()];
+            (doc.end_byte(), body.end_byte(), format!("\n{}{}", indent, PYTHON_PLACEHOLDER))
+        }
+        None => (body.start_byte(), body.end_byte(), PYTHON_PLACEHOLDER.to_string()),
+    }
+}
+
+// Rebuilds the lines with the elisions applied. A line that swallowed an elided
+// body keeps the number of the line where the body started.
+fn splice(lines: &[SourceLine], code: &str, elisions: &[(usize, usize, String)]) -> Vec<SourceLine> {
+    let mut out = Vec::new();
+    let mut line_idx = 0;
+    let mut current = String::new();
+    let mut current_number = lines[0].number;
+    let mut pos = 0;
+
+    let tail = (code.len(), code.len(), String::new());
+    for (start, end, placeholder) in elisions.iter().chain(std::iter::once(&tail)) {
+        for (i, piece) in code[pos..*start].split('\n').enumerate() {
+            if i > 0 {
+                out.push(SourceLine { number: current_number, text: std::mem::take(&mut current) });
+                line_idx += 1;
+                current_number = lines[line_idx].number;
+            }
+            current.push_str(piece);
+        }
+        for (i, piece) in placeholder.split('\n').enumerate() {
+            if i > 0 {
+                out.push(SourceLine { number: current_number, text: std::mem::take(&mut current) });
+                current_number = None;
+            }
+            current.push_str(piece);
+        }
+        line_idx += code[*start..*end].matches('\n').count();
+        pos = *end;
+    }
+    out.push(SourceLine { number: current_number, text: current });
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline(code: &str, extension: &str) -> Vec<(Option<usize>, String)> {
+        let lines: Vec<SourceLine> = code.lines()
+            .enumerate()
+            .map(|(idx, text)| SourceLine { number: Some(idx + 1), text: text.to_string() })
+            .collect();
+        skeletonize(&lines, extension).unwrap().into_iter().map(|l| (l.number, l.text)).collect()
+    }
+
+    fn numbered(lines: &[(usize, &str)]) -> Vec<(Option<usize>, String)> {
+        lines.iter().map(|(n, text)| (Some(*n), text.to_string())).collect()
+    }
+
+    #[test]
+    fn rust_functions_and_methods() {
+        let code = "use std::fmt;\n\n/// Adds.\npub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nstruct S;\n\nimpl S {\n    fn new() -> Self {\n        S\n    }\n}\n";
+        assert_eq!(outline(code, "rs"), numbered(&[
+            (1, "use std::fmt;"),
+            (2, ""),
+            (3, "/// Adds."),
+            (4, "pub fn add(a: i32, b: i32) -> i32 { ... }"),
+            (7, ""),
+            (8, "struct S;"),
+            (9, ""),
+            (10, "impl S {"),
+            (11, "    fn new() -> Self { ... }"),
+            (14, "}"),
+        ]));
+    }
+
+    #[test]
+    fn typescript_keeps_expression_bodies() {
+        let code = "import { x } from './x';\nexport function f(a: number): number {\n  return a;\n}\nconst g = (b: string) => b.length;\nclass C {\n  m(): void {\n    console.log(1);\n  }\n}\n";
+        assert_eq!(outline(code, "ts"), numbered(&[
+            (1, "import { x } from './x';"),
+            (2, "export function f(a: number): number { ... }"),
+            (5, "const g = (b: string) => b.length;"),
+            (6, "class C {"),
+            (7, "  m(): void { ... }"),
+            (10, "}"),
+        ]));
+    }
+
+    #[test]
+    fn python_keeps_docstrings() {
+        let code = "import os\n\ndef f(x):\n    \"\"\"Doc.\"\"\"\n    return x\n\nclass A:\n    def m(self):\n        pass\n";
+        assert_eq!(outline(code, "py"), vec![
+            (Some(1), "import os".to_string()),
+            (Some(2), String::new()),
+            (Some(3), "def f(x):".to_string()),
+            (Some(4), "    \"\"\"Doc.\"\"\"".to_string()),
+            (None, "    ...".to_string()), // Placeholder line that is not in the file
+            (Some(6), String::new()),
+            (Some(7), "class A:".to_string()),
+            (Some(8), "    def m(self):".to_string()),
+            (Some(9), "        ...".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn go_functions_and_methods() {
+        let code = "package main\n\nimport \"fmt\"\n\nfunc main() {\n\tfmt.Println(\"hi\")\n}\n\nfunc (s *S) Name() string {\n\treturn s.name\n}\n";
+        assert_eq!(outline(code, "go"), numbered(&[
+            (1, "package main"),
+            (2, ""),
+            (3, "import \"fmt\""),
+            (4, ""),
+            (5, "func main() { ... }"),
+            (8, ""),
+            (9, "func (s *S) Name() string { ... }"),
+        ]));
+    }
+
+    #[test]
+    fn numbers_survive_earlier_gaps() {
+        // Lines dropped by earlier transforms keep their original numbers through the splice
+        let lines = vec![
+            SourceLine { number: Some(3), text: "fn a() {".to_string() },
+            SourceLine { number: Some(4), text: "    b();".to_string() },
+            SourceLine { number: Some(5), text: "}".to_string() },
+            SourceLine { number: Some(9), text: "fn c() {}".to_string() },
+        ];
+        let outline: Vec<(Option<usize>, String)> = skeletonize(&lines, "rs").unwrap().into_iter().map(|l| (l.number, l.text)).collect();
+        assert_eq!(outline, numbered(&[(3, "fn a() { ... }"), (9, "fn c() { ... }")]));
+    }
+
+    #[test]
+    fn unsupported_languages() {
+        assert!(!supports("md"));
+        assert!(skeletonize(&[SourceLine { number: Some(1), text: "# Title".to_string() }], "md").is_none());
+    }
+}
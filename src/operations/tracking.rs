@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::models::dir_node::DirNode;
+use crate::models::export_snapshot::ExportSnapshot;
+use crate::models::export_options::ExportOptions;
+use crate::models::file_node::FileNode;
+use crate::models::profile::{ApplyMode, SelectionEntry};
+use super::{content, export, selection};
+use super::export::ExportDocument;
+
+pub const LAST_EXPORT_FILE: &str = "last_export.json";
+
+// How the project differs from the last export
+#[derive(Clone, Debug, Default)]
+pub struct ExportChanges {
+    pub modified: HashSet<String>, // Exported files whose content changed since
+    pub added: HashSet<String>,    // Files that did not exist in the project then
+    pub deleted: Vec<String>,      // Exported files that are gone
+}
+
+impl ExportChanges {
+    pub fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.added.is_empty() && self.deleted.is_empty()
+    }
+
+    // Modified and added files, sorted
+    pub fn changed(&self) -> Vec<String> {
+        let mut changed: Vec<String> = self.modified.iter().chain(&self.added).cloned().collect();
+        changed.sort();
+        changed
+    }
+}
+
+fn hash_file(file: &FileNode) -> Option<String> {
+    content::read_bytes(file).ok().map(|bytes| export::sha256(&bytes))
+}
+
+// Baseline after an export, from the hashes of what it read and the files the project has now
+pub fn snapshot(root: &DirNode, hashes: BTreeMap<String, String>) -> ExportSnapshot {
+    let mut project_files = BTreeSet::new();
+    collect_rel_paths(root, &mut project_files);
+    ExportSnapshot {
+        exported_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        files: hashes,
+        project_files,
+    }
+}
+
+fn collect_rel_paths(dir: &DirNode, out: &mut BTreeSet<String>) {
+    out.extend(dir.children_files.iter().map(|file| selection::normalize_rel_path(&file.rel_path)));
+    for sub in &dir.children_dirs {
+        collect_rel_paths(sub, out);
+    }
+}
+
+// Re-hashes the exported files that still exist, and lists files created since
+pub fn compare(root: &DirNode, snapshot: &ExportSnapshot) -> ExportChanges {
+    let mut changes = ExportChanges::default();
+    if !snapshot.project_files.is_empty() {
+        let mut current = BTreeSet::new();
+        collect_rel_paths(root, &mut current);
+        changes.added = current.into_iter()
+            .filter(|rel_path| !snapshot.project_files.contains(rel_path) && !snapshot.files.contains_key(rel_path))
+            .collect();
+    }
+    for (rel_path, hash) in &snapshot.files {
+        match selection::find_file_by_rel_path(root, rel_path) {
+            None => changes.deleted.push(rel_path.clone()),
+            Some(file) => {
+                if hash_file(file).as_ref() != Some(hash) {
+                    changes.modified.insert(rel_path.clone());
+                }
+            }
+        }
+    }
+    changes
+}
+
+// Export of what changed since the snapshot: modified and added files (whole, even if not
+// selected), selected files the last export did not have, and a list of deleted ones
+pub fn build_delta(root: &DirNode, snapshot: &ExportSnapshot, changes: &ExportChanges, options: &ExportOptions) -> ExportDocument {
+    let mut selected = Vec::new();
+    selection::collect_selected_entries(root, &mut selected);
+    let mut entries: Vec<SelectionEntry> = selected.into_iter()
+        .filter(|e| changes.modified.contains(&e.rel_path) || !snapshot.files.contains_key(&e.rel_path))
+        .collect();
+    for rel_path in changes.changed() {
+        if !entries.iter().any(|e| e.rel_path == rel_path) {
+            entries.push(SelectionEntry { rel_path, ranges: Vec::new() });
+        }
+    }
+
+    let mut delta_root = root.clone();
+    selection::apply_entries(&mut delta_root, &entries, ApplyMode::Replace);
+    let mut document = if entries.is_empty() { ExportDocument::default() } else { export::build_export(&delta_root, options) };
+
+    if !changes.deleted.is_empty() {
+        let list: String = changes.deleted.iter().map(|rel_path| format!("{}\n", rel_path)).collect();
+        export::push_section(&mut document, "DELETED SINCE LAST EXPORT", &list);
+    }
+    document
+}
+
+// Baseline after a delta export: the old one minus deleted files, plus what the delta exported
+pub fn advance(root: &DirNode, snapshot: &ExportSnapshot, changes: &ExportChanges, exported: &BTreeMap<String, String>) -> ExportSnapshot {
+    let mut files: BTreeMap<String, String> = snapshot.files.iter()
+        .filter(|(rel_path, _)| !changes.deleted.contains(rel_path))
+        .map(|(rel_path, hash)| (rel_path.clone(), hash.clone()))
+        .collect();
+    files.extend(exported.iter().map(|(rel_path, hash)| (rel_path.clone(), hash.clone())));
+    self::snapshot(root, files)
+}
+
+// A delta export with the baseline it leads to; compared right before building, so
+// changes made since the last check are not lost
+pub struct Delta {
+    pub document: ExportDocument,
+    pub snapshot: ExportSnapshot,
+}
+
+pub fn export_delta(root: &DirNode, snapshot: &ExportSnapshot, options: &ExportOptions) -> Delta {
+    let changes = compare(root, snapshot);
+    let document = build_delta(root, snapshot, &changes, options);
+    let next = advance(root, snapshot, &changes, &document.hashes);
+    Delta { document, snapshot: next }
+}
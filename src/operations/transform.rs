@@ -0,0 +1,479 @@
+use crate::models::export_options::{LineNumberOptions, LineNumberStyle, TransformOptions};
+
+// Comment/string syntax of a language family. Only what is needed to find comments
+// without being fooled by comment markers inside string literals.
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comments: &'static [(&'static str, &'static str)],
+    strings: &'static [&'static str], // Longest delimiters first ("\"\"\"" before "\"")
+    raw_strings: &'static [&'static str], // No escapes inside (Go backticks)
+    rust_literals: bool,              // Raw strings (r#"..."#), char literals vs lifetimes
+    hash_attributes: bool,            // "#[" opens an attribute, not a "#" comment (PHP 8)
+}
+
+const C_LIKE: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    strings: &["\"", "'", "`"],
+    raw_strings: &[],
+    rust_literals: false,
+    hash_attributes: false,
+};
+
+const GO: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    strings: &["\"", "'"],
+    raw_strings: &["`"],
+    rust_literals: false,
+    hash_attributes: false,
+};
+
+const RUST: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    strings: &["\""],
+    raw_strings: &[],
+    rust_literals: true,
+    hash_attributes: false,
+};
+
+const PHP: Syntax = Syntax {
+    line_comments: &["//", "#"],
+    block_comments: &[("/*", "*/")],
+    strings: &["\"", "'"],
+    raw_strings: &[],
+    rust_literals: false,
+    hash_attributes: true,
+};
+
+const CSS: Syntax = Syntax {
+    line_comments: &[],
+    block_comments: &[("/*", "*/")],
+    strings: &["\"", "'"],
+    raw_strings: &[],
+    rust_literals: false,
+    hash_attributes: false,
+};
+
+const HASH: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    strings: &["\"", "'"],
+    raw_strings: &[],
+    rust_literals: false,
+    hash_attributes: false,
+};
+
+const PYTHON: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    strings: &["\"\"\"", "'''", "\"", "'"],
+    raw_strings: &[],
+    rust_literals: false,
+    hash_attributes: false,
+};
+
+const SQL: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comments: &[("/*", "*/")],
+    strings: &["'", "\""],
+    raw_strings: &[],
+    rust_literals: false,
+    hash_attributes: false,
+};
+
+const LUA: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comments: &[("--[[", "]]")],
+    strings: &["\"", "'"],
+    raw_strings: &[],
+    rust_literals: false,
+    hash_attributes: false,
+};
+
+const MARKUP: Syntax = Syntax {
+    line_comments: &[],
+    block_comments: &[("<!--", "-->")],
+    strings: &[],
+    raw_strings: &[],
+    rust_literals: false,
+    hash_attributes: false,
+};
+
+const BLADE: Syntax = Syntax {
+    line_comments: &[],
+    block_comments: &[("{{--", "--}}"), ("<!--", "-->")],
+    strings: &[],
+    raw_strings: &[],
+    rust_literals: false,
+    hash_attributes: false,
+};
+
+const LICENSE_MARKERS: &[&str] = &["copyright", "license", "licence", "spdx-license-identifier", "all rights reserved"];
+
+fn syntax_for(extension: &str) -> Option<&'static Syntax> {
+    match extension {
+        "rs" => Some(&RUST),
+        "c" | "h" | "cpp" | "hpp" | "cc" | "cxx" | "cs" | "java" | "kt" | "kts" | "scala" | "swift"
+        | "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" | "dart" | "scss" | "less" => Some(&C_LIKE),
+        "go" => Some(&GO),
+        "php" => Some(&PHP),
+        "css" => Some(&CSS),
+        "py" | "pyw" => Some(&PYTHON),
+        "rb" | "sh" | "bash" | "zsh" | "toml" | "yaml" | "yml" | "r" | "pl" | "env" | "ini" | "conf" => Some(&HASH),
+        "sql" => Some(&SQL),
+        "lua" => Some(&LUA),
+        "html" | "htm" | "xml" | "svg" | "vue" | "svelte" => Some(&MARKUP),
+        "blade.php" => Some(&BLADE),
+        _ => None,
+    }
+}
+
+// A line of exported text. `number` is the 1-based line in the original file,
+// or None for lines that do not come from the file.
+pub struct SourceLine {
+    pub number: Option<usize>,
+    pub text: String,
+}
+
+// Applies the enabled transforms. Line structure is kept intact until the
+// line-level passes, so every surviving line still maps to its original number.
+pub fn transform_lines(code: &str, extension: &str, options: &TransformOptions) -> Vec<SourceLine> {
+    if options.is_identity() {
+        return code.lines()
+            .enumerate()
+            .map(|(idx, text)| SourceLine { number: Some(idx + 1), text: text.to_string() })
+            .collect();
+    }
+
+    let syntax = syntax_for(extension);
+
+    // 1. Decide which comment spans to drop
+    let mut removed: Vec<(usize, usize)> = Vec::new();
+    if let Some(syntax) = syntax {
+        let spans = comment_spans(code, syntax);
+        if options.strip_comments {
+            removed = spans;
+        } else if options.strip_license {
+            removed = license_banner(code, &spans);
+        }
+    }
+
+    // 2. Cut them out, keeping the newlines they contained
+    let stripped = remove_spans(code, &removed);
+
+    // 3. Line-level passes
+    let mut result: Vec<SourceLine> = Vec::new();
+    for (idx, (original, line)) in code.lines().zip(stripped.lines()).enumerate() {
+        let emptied = line.trim().is_empty() && !original.trim().is_empty();
+        if emptied {
+            continue; // Line held nothing but a comment
+        }
+
+        let touched = line.len() != original.len();
+        let text = if options.trim_trailing_whitespace || touched { line.trim_end() } else { line };
+
+        // Blank lines at the very top are only left over from a removed header
+        let leading_blank = result.is_empty() && !removed.is_empty();
+        let redundant_blank = text.trim().is_empty()
+            && (leading_blank
+                || (options.collapse_blank_lines && result.last().is_none_or(|prev| prev.text.trim().is_empty())));
+        if redundant_blank {
+            continue;
+        }
+        result.push(SourceLine { number: Some(idx + 1), text: text.to_string() });
+    }
+
+    if options.collapse_blank_lines {
+        while result.last().is_some_and(|line| line.text.trim().is_empty()) {
+            result.pop();
+        }
+    }
+    result
+}
+
+// Blanks out comments but keeps every newline (for code analysis, not for export)
+pub fn strip_comments(code: &str, extension: &str) -> String {
+    match syntax_for(extension) {
+        Some(syntax) => remove_spans(code, &comment_spans(code, syntax)),
+        None => code.to_string(),
+    }
+}
+
+// Like strip_comments, but string and char literals go too (delimiters included)
+pub fn strip_comments_and_strings(code: &str, extension: &str) -> String {
+    match syntax_for(extension) {
+        Some(syntax) => remove_spans(code, &literal_spans(code, syntax, true)),
+        None => code.to_string(),
+    }
+}
+
+// "\r\n" for files with Windows line endings (judged by the first line), otherwise "\n"
+pub fn line_ending(text: &str) -> &'static str {
+    match text.find('\n') {
+        Some(pos) if text[..pos].ends_with('\r') => "\r\n",
+        _ => "\n",
+    }
+}
+
+pub fn join_lines(lines: &[SourceLine], trailing_newline: bool) -> String {
+    let mut out = lines.iter().map(|line| line.text.as_str()).collect::<Vec<_>>().join("\n");
+    if trailing_newline && !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+// Prefixes numbered lines; lines without a number get a blank gutter of the same width
+pub fn number_lines(lines: &[SourceLine], options: &LineNumberOptions) -> String {
+    let max = lines.iter().filter_map(|l| l.number).max().unwrap_or(0);
+    let width = options.width.max(max.to_string().len());
+
+    let mut out = String::new();
+    for line in lines {
+        let number = line.number.map(|n| n.to_string()).unwrap_or_default();
+        match options.style {
+            LineNumberStyle::Pipe => out.push_str(&format!("{:>width$} | {}", number, line.text)),
+            LineNumberStyle::Colon => out.push_str(&format!("{:>width$}: {}", number, line.text)),
+            LineNumberStyle::Tab => out.push_str(&format!("{:>width$}\t{}", number, line.text)),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn remove_spans(code: &str, spans: &[(usize, usize)]) -> String {
+    let mut out = String::with_capacity(code.len());
+    let mut pos = 0;
+    for &(start, end) in spans {
+        out.push_str(&code[pos..start]);
+        for _ in code[start..end].matches('\n') {
+            out.push('\n');
+        }
+        pos = end;
+    }
+    out.push_str(&code[pos..]);
+    out
+}
+
+// The first block of comments at the top of the file (after an optional shebang),
+// if it looks like a license header. A blank line ends the block.
+fn license_banner(code: &str, spans: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut block: Vec<(usize, usize)> = Vec::new();
+    let mut pos = 0;
+    for &(start, end) in spans {
+        let gap = &code[pos..start];
+        if !gap.trim().is_empty() {
+            break;
+        }
+        if !block.is_empty() && gap.matches('\n').count() > 1 {
+            break;
+        }
+        if block.is_empty() && code[start..end].starts_with("#!") {
+            pos = end;
+            continue;
+        }
+        block.push((start, end));
+        pos = end;
+    }
+
+    let text = block.iter().map(|&(s, e)| &code[s..e]).collect::<String>().to_lowercase();
+    if LICENSE_MARKERS.iter().any(|m| text.contains(m)) {
+        block
+    } else {
+        Vec::new()
+    }
+}
+
+// Byte ranges of every comment in `code`. Line comments stop before the newline.
+fn comment_spans(code: &str, syntax: &Syntax) -> Vec<(usize, usize)> {
+    literal_spans(code, syntax, false)
+}
+
+// Comments, plus string and char literals when `strings` is set
+fn literal_spans(code: &str, syntax: &Syntax, strings: bool) -> Vec<(usize, usize)> {
+    let bytes = code.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    'outer: while i < bytes.len() {
+        let rest = &code[i..];
+
+        if syntax.rust_literals {
+            if let Some(len) = rust_raw_string_len(rest, code[..i].chars().next_back()) {
+                if strings {
+                    spans.push((i, i + len));
+                }
+                i += len;
+                continue;
+            }
+            if rest.starts_with('\'') {
+                let len = rust_char_literal_len(rest);
+                if strings && len > 1 {
+                    spans.push((i, i + len));
+                }
+                i += len;
+                continue;
+            }
+        }
+
+        for (open, close) in syntax.block_comments {
+            if let Some(body) = rest.strip_prefix(open) {
+                let end = body.find(close).map(|p| i + open.len() + p + close.len()).unwrap_or(bytes.len());
+                spans.push((i, end));
+                i = end;
+                continue 'outer;
+            }
+        }
+
+        for marker in syntax.line_comments {
+            if rest.starts_with(marker) && !(*marker == "#" && syntax.hash_attributes && rest.starts_with("#[")) {
+                let end = rest.find('\n').map(|p| i + p).unwrap_or(bytes.len());
+                spans.push((i, end));
+                i = end;
+                continue 'outer;
+            }
+        }
+
+        for delim in syntax.raw_strings {
+            if let Some(body) = rest.strip_prefix(delim) {
+                let len = body.find(delim).map(|p| delim.len() * 2 + p).unwrap_or(rest.len());
+                if strings {
+                    spans.push((i, i + len));
+                }
+                i += len;
+                continue 'outer;
+            }
+        }
+
+        for delim in syntax.strings {
+            if rest.starts_with(delim) {
+                let len = string_literal_len(rest, delim);
+                if strings {
+                    spans.push((i, i + len));
+                }
+                i += len;
+                continue 'outer;
+            }
+        }
+
+        i += rest.chars().next().map(|c| c.len_utf8()).unwrap_or(1);
+    }
+    spans
+}
+
+// Length of a string literal starting at the beginning of `rest`, honoring backslash escapes
+fn string_literal_len(rest: &str, delim: &str) -> usize {
+    let mut j = delim.len();
+    while j < rest.len() {
+        let tail = &rest[j..];
+        if let Some(escaped) = tail.strip_prefix('\\') {
+            j += 1 + escaped.chars().next().map(|c| c.len_utf8()).unwrap_or(0);
+        } else if tail.starts_with(delim) {
+            return j + delim.len();
+        } else {
+            j += tail.chars().next().map(|c| c.len_utf8()).unwrap_or(1);
+        }
+    }
+    rest.len()
+}
+
+// r"...", r#"..."#, br##"..."## (only when `r` is not the tail of an identifier)
+fn rust_raw_string_len(rest: &str, prev: Option<char>) -> Option<usize> {
+    if prev.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    let after_prefix = rest.strip_prefix("br").or_else(|| rest.strip_prefix('r'))?;
+    let hashes = after_prefix.chars().take_while(|&c| c == '#').count();
+    if !after_prefix[hashes..].starts_with('"') {
+        return None;
+    }
+    let prefix_len = rest.len() - after_prefix.len() + hashes + 1;
+    let closing = format!("\"{}", "#".repeat(hashes));
+    Some(rest[prefix_len..].find(&closing).map(|p| prefix_len + p + closing.len()).unwrap_or(rest.len()))
+}
+
+// 'a' and '\n' are literals, 'a (lifetime / label) is not
+fn rust_char_literal_len(rest: &str) -> usize {
+    let mut chars = rest[1..].char_indices();
+    match chars.next() {
+        Some((_, '\\')) => {
+            // Skip the escaped char itself, so '\'' ends at the right quote; '\u{..}' runs on to the closing one
+            let escaped = rest[2..].chars().next().map(|c| c.len_utf8()).unwrap_or(0);
+            rest[2 + escaped..].find('\'').map(|p| 2 + escaped + p + 1).unwrap_or(1)
+        }
+        Some((_, c)) => {
+            let after = 1 + c.len_utf8();
+            if rest[after..].starts_with('\'') { after + 1 } else { 1 }
+        }
+        None => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comments<'a>(code: &'a str, extension: &str) -> Vec<&'a str> {
+        let syntax = syntax_for(extension).unwrap();
+        comment_spans(code, syntax).into_iter().map(|(s, e)| &code[s..e]).collect()
+    }
+
+    #[test]
+    fn line_and_block_comments() {
+        let code = "let a = 1; // one\n/* two\n lines */ let b = 2;\n";
+        assert_eq!(comments(code, "rs"), vec!["// one", "/* two\n lines */"]);
+    }
+
+    #[test]
+    fn markers_inside_strings_are_not_comments() {
+        assert!(comments("let url = \"http://x\"; let s = \"/* no */\";", "rs").is_empty());
+        assert!(comments("const s = 'it\\'s // fine';", "js").is_empty());
+    }
+
+    #[test]
+    fn rust_char_literals() {
+        assert_eq!(comments("let q = '\\''; // quote", "rs"), vec!["// quote"]);
+        assert_eq!(comments("let c = '\"'; // dq", "rs"), vec!["// dq"]);
+        assert_eq!(comments("let n = '\\n'; let u = '\\u{1F600}'; // esc", "rs"), vec!["// esc"]);
+        assert_eq!(comments("let c = '/'; // slash", "rs"), vec!["// slash"]);
+    }
+
+    #[test]
+    fn rust_lifetimes_are_not_char_literals() {
+        assert_eq!(comments("fn f<'a>(x: &'a str) {} // life", "rs"), vec!["// life"]);
+    }
+
+    #[test]
+    fn rust_raw_strings() {
+        assert_eq!(comments("let s = r#\"// \"quoted\" /*\"#; // after", "rs"), vec!["// after"]);
+        // `r` at the end of an identifier does not start a raw string
+        assert_eq!(comments("let bar = \"x\"; // c", "rs"), vec!["// c"]);
+    }
+
+    #[test]
+    fn hash_comments() {
+        assert_eq!(comments("x = 1 # note\n", "py"), vec!["# note"]);
+        assert_eq!(comments("a: 1 # note\n", "yaml"), vec!["# note"]);
+    }
+
+    #[test]
+    fn php_attributes_are_not_hash_comments() {
+        let code = "<?php\n#[Route('/home')]\nfunction home() {} # old style\n// new style\n";
+        assert_eq!(comments(code, "php"), vec!["# old style", "// new style"]);
+    }
+
+    #[test]
+    fn go_raw_strings_have_no_escapes() {
+        // The backslash does not escape the closing backtick, so the comment after it is found
+        assert_eq!(comments("p := `C:\\dir\\`; // path", "go"), vec!["// path"]);
+        assert_eq!(comments("s := `// not a comment`", "go"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn unterminated_block_runs_to_end() {
+        let code = "a /* open";
+        assert_eq!(comments(code, "c"), vec!["/* open"]);
+    }
+}
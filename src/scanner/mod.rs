@@ -1,4 +1,5 @@
 pub mod thread;
+pub mod revision;
 
 use crate::models::dir_node::DirNode;
 
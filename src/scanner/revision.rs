@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::sync::mpsc::Sender;
+use crate::models::{dir_node::DirNode, file_node::FileNode, git_blob::GitBlob};
+use crate::operations::git;
+use super::ScanMessage;
+use super::thread::{count_lines_in, is_ignored, is_own_output};
+
+// Builds the tree of the project as it was at `rev`, from the git object database.
+// Same filters as the disk scanner; file contents are read from blobs later on.
+pub fn read_revision_tree(
+    root_path: &Path,
+    rev: &str,
+    tx: &Sender<ScanMessage>,
+    cancel_flag: &Arc<AtomicBool>,
+) -> Option<DirNode> {
+    let entries = git::list_tree(root_path, rev).ok()?;
+    let entries: Vec<(String, String, u64)> = entries.into_iter()
+        .filter(|(rel_path, _, _)| {
+            let mut parts = rel_path.split('/').rev();
+            let name = parts.next().unwrap_or_default();
+            !is_ignored(name) && !is_own_output(name) && !parts.any(is_ignored)
+        })
+        .collect();
+    let _ = tx.send(ScanMessage::Progress(entries.len()));
+
+    let ids: Vec<String> = entries.iter().map(|(_, id, _)| id.clone()).collect();
+    let mut line_counts = HashMap::new();
+    git::for_each_blob(root_path, &ids, cancel_flag, |index, bytes| {
+        line_counts.insert(ids[index].clone(), count_lines_in(bytes));
+    }).ok()?;
+    if cancel_flag.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    let mut root = DirNode {
+        name: root_path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+        path: root_path.to_path_buf(),
+        children_dirs: Vec::new(),
+        children_files: Vec::new(),
+    };
+    for (rel_path, id, size_bytes) in entries {
+        let parts: Vec<&str> = rel_path.split('/').collect();
+        let (name, dirs) = parts.split_last()?;
+        let name = name.to_string();
+        let extension = if name.to_lowercase().ends_with(".blade.php") {
+            "blade.php".to_string()
+        } else {
+            Path::new(&name).extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default()
+        };
+
+        let file = FileNode {
+            path: root_path.join(&rel_path),
+            rel_path: rel_path.clone(),
+            extension,
+            selected: false,
+            size_bytes,
+            line_count: line_counts.get(&id).copied(),
+            skeleton: false,
+            line_ranges: Vec::new(),
+            git_status: None,
+            blob: Some(GitBlob { repo_dir: root_path.to_path_buf(), id }),
+            name,
+        };
+        dir_for(&mut root, dirs).children_files.push(file);
+    }
+
+    sort_tree(&mut root);
+    Some(root)
+}
+
+// Finds or creates the folder for `dirs` below `root`
+fn dir_for<'a>(root: &'a mut DirNode, dirs: &[&str]) -> &'a mut DirNode {
+    let mut dir = root;
+    for name in dirs {
+        let pos = match dir.children_dirs.iter().position(|d| d.name == *name) {
+            Some(pos) => pos,
+            None => {
+                dir.children_dirs.push(DirNode {
+                    name: name.to_string(),
+                    path: dir.path.join(name),
+                    children_dirs: Vec::new(),
+                    children_files: Vec::new(),
+                });
+                dir.children_dirs.len() - 1
+            }
+        };
+        dir = &mut dir.children_dirs[pos];
+    }
+    dir
+}
+
+fn sort_tree(dir: &mut DirNode) {
+    dir.children_dirs.sort_by(|a, b| a.name.cmp(&b.name));
+    dir.children_files.sort_by(|a, b| a.name.cmp(&b.name));
+    for sub in &mut dir.children_dirs {
+        sort_tree(sub);
+    }
+}
@@ -4,7 +4,7 @@ use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 use std::sync::mpsc::Sender;
 use crate::models::{dir_node::DirNode, file_node::FileNode};
 use super::ScanMessage;
-use crate::operations::export::DEFAULT_OUTPUT_FILENAME;
+use crate::operations::export::{DEFAULT_OUTPUT_FILENAME, MANIFEST_FILENAME};
 
 const IGNORE_DIRS: &[&str] = &[
     ".git", ".vscode", "node_modules", "vendor", "__pycache__", 
@@ -39,8 +39,7 @@ pub fn read_dir_recursive_threaded(
             let path = entry.path();
             let name = entry.file_name().to_string_lossy().to_string();
 
-            if name.starts_with('.') && name != ".env" { continue; }
-            if IGNORE_DIRS.contains(&name.as_str()) { continue; }
+            if is_ignored(&name) { continue; }
 
             if path.is_dir() {
                 // Recurse
@@ -62,7 +61,7 @@ pub fn read_dir_recursive_threaded(
                     .to_string_lossy()
                     .to_string();
 
-                if name == DEFAULT_OUTPUT_FILENAME { continue; }
+                if is_own_output(&name) { continue; }
 
                 let size_bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
 
@@ -73,6 +72,11 @@ pub fn read_dir_recursive_threaded(
                     extension,
                     selected: false,
                     size_bytes,
+                    line_count: None,
+                    skeleton: false,
+                    line_ranges: Vec::new(),
+                    git_status: None,
+                    blob: None,
                 });
 
                 // NOTIFY UI: Found a file
@@ -85,4 +89,19 @@ pub fn read_dir_recursive_threaded(
     node.children_dirs.sort_by(|a, b| a.name.cmp(&b.name));
     node.children_files.sort_by(|a, b| a.name.cmp(&b.name));
     Some(node)
-}
\ No newline at end of file
+}
+
+// Hidden entries and build / dependency folders
+pub fn is_ignored(name: &str) -> bool {
+    (name.starts_with('.') && name != ".env") || IGNORE_DIRS.contains(&name)
+}
+
+// Files written by our own exports
+pub fn is_own_output(name: &str) -> bool {
+    name == DEFAULT_OUTPUT_FILENAME || name == MANIFEST_FILENAME
+}
+
+pub fn count_lines_in(bytes: &[u8]) -> usize {
+    let newlines = bytes.iter().filter(|&&b| b == b'\n').count();
+    if bytes.last().is_some_and(|&b| b != b'\n') { newlines + 1 } else { newlines }
+}
@@ -0,0 +1,210 @@
+use eframe::egui;
+use similar::{ChangeTag, TextDiff};
+use crate::app::CodeCollectorApp;
+use crate::models::dir_node::DirNode;
+use crate::operations::{content, response, selection};
+use crate::operations::response::BlockTarget;
+
+// One parsed file block, matched against the project
+pub struct PendingChange {
+    pub path: String,             // As written in the response
+    pub target: BlockTarget,
+    pub new_content: String,
+    pub accepted: bool,
+    pub note: Option<String>,     // Why it is not accepted by default
+    pub diff: Vec<(char, String)>, // ('+' | '-' | ' ' | '@', line)
+    pub added: usize,
+    pub removed: usize,
+}
+
+#[derive(Default)]
+pub struct ApplyResponseDialog {
+    pub input: String,
+    pub changes: Option<Vec<PendingChange>>, // None = still pasting
+    pub current: usize,
+}
+
+impl ApplyResponseDialog {
+    fn parse(&mut self, root: &DirNode) {
+        let changes = response::parse_response(&self.input).into_iter().map(|block| {
+            let target = response::resolve_target(root, &block.path);
+            let old = match &target {
+                BlockTarget::Existing(rel_path) => selection::find_file_by_rel_path(root, rel_path)
+                    .and_then(|file| content::read_string(file).ok()),
+                _ => None,
+            };
+            pending_change(block, target, old)
+        }).collect();
+        self.changes = Some(changes);
+        self.current = 0;
+    }
+}
+
+// Unified diff as (tag, line) rows plus added / removed counts
+pub fn line_diff(old: &str, new: &str) -> (Vec<(char, String)>, usize, usize) {
+    let mut diff = Vec::new();
+    let (mut added, mut removed) = (0, 0);
+    let text_diff = TextDiff::from_lines(old, new);
+    for hunk in text_diff.unified_diff().context_radius(3).iter_hunks() {
+        diff.push(('@', hunk.header().to_string()));
+        for change in hunk.iter_changes() {
+            let tag = match change.tag() {
+                ChangeTag::Insert => { added += 1; '+' }
+                ChangeTag::Delete => { removed += 1; '-' }
+                ChangeTag::Equal => ' ',
+            };
+            diff.push((tag, change.value().trim_end_matches(['\r', '\n']).to_string()));
+        }
+    }
+    (diff, added, removed)
+}
+
+// Colored rows of a `line_diff`
+pub fn show_diff_rows(ui: &mut egui::Ui, diff: &[(char, String)]) {
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    egui::ScrollArea::both().auto_shrink(false).show_rows(ui, row_height, diff.len(), |ui, rows| {
+        for (tag, line) in &diff[rows] {
+            let color = match tag {
+                '+' => egui::Color32::from_rgb(52, 199, 89),
+                '-' => egui::Color32::from_rgb(255, 59, 48),
+                '@' => egui::Color32::from_rgb(90, 150, 255),
+                _ => ui.visuals().weak_text_color(),
+            };
+            let text = if *tag == '@' { line.clone() } else { format!("{}{}", tag, line) };
+            ui.label(egui::RichText::new(text).monospace().color(color));
+        }
+    });
+}
+
+fn pending_change(mut block: response::ResponseBlock, target: BlockTarget, old: Option<String>) -> PendingChange {
+    if let Some(old) = &old {
+        block.content = response::match_line_endings(&block.content, old);
+    }
+    let (diff, added, removed) = line_diff(old.as_deref().unwrap_or_default(), &block.content);
+
+    let note = match &target {
+        BlockTarget::Invalid(reason) => Some(reason.clone()),
+        _ if block.partial => Some("Exported as a diff or signatures only; not a whole file".to_string()),
+        _ if response::looks_partial(&block.content) => Some("Contains omission markers; not a whole file".to_string()),
+        BlockTarget::Existing(_) if old.is_none() => Some("Cannot read the current file".to_string()),
+        BlockTarget::Existing(_) if added == 0 && removed == 0 => Some("No changes".to_string()),
+        BlockTarget::New(_) => Some("New file".to_string()),
+        _ => None,
+    };
+    // New files are allowed, but only when ticked explicitly
+    let accepted = note.is_none();
+
+    PendingChange { path: block.path, target, new_content: block.content, accepted, note, diff, added, removed }
+}
+
+pub fn show_apply_response_window(ctx: &egui::Context, app: &mut CodeCollectorApp) {
+    let Some(dialog) = &mut app.apply_dialog else { return; };
+    let Some(root) = &app.root_node else { return; };
+
+    let mut open = true;
+    let mut apply = None;
+
+    egui::Window::new("📥 Apply Response")
+        .open(&mut open)
+        .default_size([900.0, 600.0])
+        .resizable(true)
+        .show(ctx, |ui| {
+            let Some(changes) = &mut dialog.changes else {
+                ui.label("Paste a reply that contains whole files as FILE: banners, XML <document> blocks or Markdown code fences labelled with a path.");
+                egui::ScrollArea::vertical().max_height(ui.available_height() - 40.0).show(ui, |ui| {
+                    ui.add(egui::TextEdit::multiline(&mut dialog.input)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(25));
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("📋 Paste").clicked() {
+                        if let Ok(text) = arboard::Clipboard::new().and_then(|mut c| c.get_text()) {
+                            dialog.input = text;
+                        }
+                    }
+                    if ui.add_enabled(!dialog.input.trim().is_empty(), egui::Button::new("Parse")).clicked() {
+                        dialog.parse(root);
+                    }
+                });
+                return;
+            };
+
+            if changes.is_empty() {
+                ui.colored_label(egui::Color32::from_rgb(255, 149, 0), "No file blocks found in the pasted text.");
+                if ui.button("Back").clicked() {
+                    dialog.changes = None;
+                }
+                return;
+            }
+
+            // --- File list ---
+            egui::SidePanel::left("apply_response_files")
+                .resizable(true)
+                .default_width(280.0)
+                .show_inside(ui, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for (idx, change) in changes.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                let valid = !matches!(change.target, BlockTarget::Invalid(_));
+                                ui.add_enabled(valid, egui::Checkbox::without_text(&mut change.accepted));
+                                let label = match &change.target {
+                                    BlockTarget::Existing(rel_path) | BlockTarget::New(rel_path) => rel_path.clone(),
+                                    BlockTarget::Invalid(_) => change.path.clone(),
+                                };
+                                if ui.selectable_label(dialog.current == idx, egui::RichText::new(label).monospace()).clicked() {
+                                    dialog.current = idx;
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.add_space(24.0);
+                                ui.label(egui::RichText::new(format!("+{} −{}", change.added, change.removed)).small().weak());
+                                if let Some(note) = &change.note {
+                                    ui.label(egui::RichText::new(note).small().color(egui::Color32::from_rgb(255, 149, 0)));
+                                }
+                            });
+                        }
+                    });
+                });
+
+            // --- Diff of the current file ---
+            let accepted = changes.iter().filter(|c| c.accepted).count();
+            ui.horizontal(|ui| {
+                if ui.button("Back").clicked() {
+                    apply = Some(None);
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.add_enabled(accepted > 0, egui::Button::new(format!("✔ Apply {} files", accepted)))
+                        .on_hover_text("Originals are backed up to .code_collector/backups")
+                        .clicked()
+                    {
+                        let writes = changes.iter()
+                            .filter(|c| c.accepted)
+                            .filter_map(|c| match &c.target {
+                                BlockTarget::Existing(rel_path) | BlockTarget::New(rel_path) => Some((rel_path.clone(), Some(c.new_content.clone()))),
+                                BlockTarget::Invalid(_) => None,
+                            })
+                            .collect::<Vec<_>>();
+                        apply = Some(Some(writes));
+                    }
+                });
+            });
+            ui.separator();
+
+            let Some(change) = changes.get(dialog.current) else { return; };
+            show_diff_rows(ui, &change.diff);
+        });
+
+    match apply {
+        Some(None) => {
+            if let Some(dialog) = &mut app.apply_dialog {
+                dialog.changes = None;
+            }
+        }
+        Some(Some(writes)) => app.apply_response_changes(&writes),
+        None => {}
+    }
+    if !open {
+        app.apply_dialog = None;
+    }
+}
@@ -0,0 +1,211 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::sync::mpsc::{Receiver, channel};
+use std::thread;
+use eframe::egui;
+use crate::app::CodeCollectorApp;
+use crate::models::dir_node::DirNode;
+use crate::operations::search::{self, ContentQuery, SearchHit, SearchMessage};
+
+// One line of the results list: a file heading or one of its hits (index into `hits`)
+#[derive(Clone, Copy)]
+pub enum ResultRow {
+    File(usize),
+    Hit(usize),
+}
+
+#[derive(Default)]
+pub struct ContentSearch {
+    pub query: ContentQuery,
+    pub hits: Vec<SearchHit>,
+    pub rows: Vec<ResultRow>, // Kept in step with `hits`, so the list only lays out visible rows
+    pub error: Option<String>,
+    pub loader: Option<Receiver<SearchMessage>>,
+    pub cancel: Option<Arc<AtomicBool>>,
+    pub finished: Option<(usize, bool)>, // (files scanned, stopped at the hit limit)
+}
+
+impl ContentSearch {
+    fn start(&mut self, root: &DirNode, ctx: &egui::Context) {
+        self.stop();
+        self.hits.clear();
+        self.rows.clear();
+        self.finished = None;
+        let regex = match self.query.compile() {
+            Ok(regex) => regex,
+            Err(e) => {
+                self.error = Some(e);
+                return;
+            }
+        };
+        self.error = None;
+
+        let mut files = Vec::new();
+        search::collect_files(root, &mut files);
+        let (tx, rx) = channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.loader = Some(rx);
+        self.cancel = Some(cancel.clone());
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            search::search_files(&files, &regex, &tx, &cancel);
+            ctx.request_repaint();
+        });
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(flag) = self.cancel.take() {
+            flag.store(true, Ordering::Relaxed);
+        }
+        self.loader = None;
+    }
+
+    fn poll(&mut self, ctx: &egui::Context) {
+        let Some(rx) = &self.loader else { return; };
+        while let Ok(msg) = rx.try_recv() {
+            match msg {
+                SearchMessage::Hits(hits) => add_hits(&mut self.hits, &mut self.rows, hits),
+                SearchMessage::Finished { scanned, truncated } => {
+                    self.finished = Some((scanned, truncated));
+                    self.loader = None;
+                    self.cancel = None;
+                    return;
+                }
+            }
+        }
+        ctx.request_repaint();
+    }
+
+    fn file_count(&self) -> usize {
+        self.rows.iter().filter(|row| matches!(row, ResultRow::File(_))).count()
+    }
+}
+
+// Hits arrive grouped by file; a heading row starts each new file
+fn add_hits(all: &mut Vec<SearchHit>, rows: &mut Vec<ResultRow>, hits: Vec<SearchHit>) {
+    for hit in hits {
+        let idx = all.len();
+        if all.last().is_none_or(|last| last.rel_path != hit.rel_path) {
+            rows.push(ResultRow::File(idx));
+        }
+        rows.push(ResultRow::Hit(idx));
+        all.push(hit);
+    }
+}
+
+fn snippet_job(hit: &SearchHit, ui: &egui::Ui) -> egui::text::LayoutJob {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let normal = egui::TextFormat { font_id: font_id.clone(), color: ui.visuals().text_color(), ..Default::default() };
+    let marked = egui::TextFormat {
+        font_id,
+        color: ui.visuals().strong_text_color(),
+        background: egui::Color32::from_rgba_unmultiplied(255, 200, 0, 70),
+        ..Default::default()
+    };
+    let (start, end) = hit.columns;
+    let mut job = egui::text::LayoutJob::default();
+    job.append(&hit.snippet[..start], 0.0, normal.clone());
+    job.append(&hit.snippet[start..end], 0.0, marked);
+    job.append(&hit.snippet[end..], 0.0, normal);
+    job
+}
+
+pub fn show_content_search_window(ctx: &egui::Context, app: &mut CodeCollectorApp) {
+    let Some(search) = &mut app.content_search else { return; };
+    let Some(root) = &app.root_node else { return; };
+    search.poll(ctx);
+
+    let mut open = true;
+    let mut jump = None;
+    let mut select_all = false;
+
+    egui::Window::new("🔎 Find in Files")
+        .open(&mut open)
+        .default_size([600.0, 500.0])
+        .resizable(true)
+        .show(ctx, |ui| {
+            let response = ui.add(egui::TextEdit::singleline(&mut search.query.text)
+                .hint_text(if search.query.regex { r"fn \w+_handler" } else { "text to find" })
+                .font(egui::TextStyle::Monospace)
+                .desired_width(f32::INFINITY));
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut search.query.regex, "Regex");
+                ui.checkbox(&mut search.query.case_sensitive, "Match case");
+                ui.checkbox(&mut search.query.whole_word, "Whole word");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if search.loader.is_some() {
+                        if ui.button("Stop").clicked() {
+                            search.stop();
+                        }
+                        ui.spinner();
+                    } else if ui.add_enabled(!search.query.text.is_empty(), egui::Button::new("Search")).clicked() || submitted {
+                        search.start(root, ctx);
+                    }
+                });
+            });
+
+            if let Some(error) = &search.error {
+                ui.colored_label(egui::Color32::from_rgb(255, 59, 48), error);
+            }
+            ui.horizontal(|ui| {
+                let summary = match search.finished {
+                    Some((scanned, truncated)) => format!(
+                        "{} matches in {} files ({} files searched{})",
+                        search.hits.len(), search.file_count(), scanned, if truncated { ", stopped at the limit" } else { "" }
+                    ),
+                    None if search.loader.is_some() => format!("{} matches so far…", search.hits.len()),
+                    None => String::new(),
+                };
+                ui.label(egui::RichText::new(summary).small().weak());
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.add_enabled(!search.hits.is_empty(), egui::Button::new("Select matching files")).clicked() {
+                        select_all = true;
+                    }
+                });
+            });
+            ui.separator();
+
+            let row_height = ui.spacing().interact_size.y.max(ui.text_style_height(&egui::TextStyle::Monospace));
+            egui::ScrollArea::vertical().auto_shrink(false).show_rows(ui, row_height, search.rows.len(), |ui, rows| {
+                for row in &search.rows[rows] {
+                    match *row {
+                        ResultRow::File(idx) => {
+                            let hit = &search.hits[idx];
+                            if ui.link(egui::RichText::new(&hit.rel_path).strong().monospace()).clicked() {
+                                jump = Some((hit.rel_path.clone(), hit.line));
+                            }
+                        }
+                        ResultRow::Hit(idx) => {
+                            let hit = &search.hits[idx];
+                            ui.horizontal(|ui| {
+                                ui.set_height(row_height);
+                                ui.add_space(12.0);
+                                let number = egui::RichText::new(format!("{:>5}", hit.line)).monospace().weak();
+                                let clicked = ui.selectable_label(false, number).clicked()
+                                    | ui.add(egui::Label::new(snippet_job(hit, ui)).sense(egui::Sense::click())).clicked();
+                                if clicked {
+                                    jump = Some((hit.rel_path.clone(), hit.line));
+                                }
+                            });
+                        }
+                    }
+                }
+            });
+        });
+
+    if select_all {
+        let mut paths: Vec<String> = search.hits.iter().map(|h| h.rel_path.clone()).collect();
+        paths.dedup();
+        app.select_rel_paths(&paths);
+    }
+    if let Some((rel_path, line)) = jump {
+        app.open_preview_at(ctx, &rel_path, line);
+    }
+    if !open {
+        if let Some(search) = &mut app.content_search {
+            search.stop();
+        }
+        app.content_search = None;
+    }
+}
@@ -0,0 +1,107 @@
+use eframe::egui;
+use crate::app::CodeCollectorApp;
+use crate::operations::export::{self, ExportDocument};
+use crate::operations::tracking;
+
+pub struct ExportPreview {
+    pub document: ExportDocument,
+    pub line_starts: Vec<usize>, // Byte offset of every line in `document.text`
+    pub tokens: usize,
+    pub scroll_to_line: Option<usize>,
+}
+
+impl ExportPreview {
+    pub fn new(document: ExportDocument) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(document.text.match_indices('\n').map(|(i, _)| i + 1));
+        let tokens = export::estimate_tokens(&document.text);
+        Self { document, line_starts, tokens, scroll_to_line: None }
+    }
+
+    fn line(&self, idx: usize) -> &str {
+        let start = self.line_starts[idx];
+        let end = self.line_starts.get(idx + 1).map(|e| e - 1).unwrap_or(self.document.text.len());
+        &self.document.text[start..end]
+    }
+
+    fn line_of_offset(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset).saturating_sub(1)
+    }
+}
+
+pub fn show_export_preview_window(ctx: &egui::Context, app: &mut CodeCollectorApp) {
+    let Some(preview) = &mut app.export_preview else { return; };
+
+    let mut open = true;
+    let mut refresh = false;
+    let mut copy = false;
+
+    egui::Window::new("👁 Export Preview")
+        .open(&mut open)
+        .default_size([800.0, 600.0])
+        .resizable(true)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(format!(
+                    "{} files · {:.2} KB · ~{} tokens",
+                    preview.document.files.len(),
+                    preview.document.text.len() as f64 / 1024.0,
+                    preview.tokens
+                )).strong());
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("📋 Copy").on_hover_text("Copies exactly what is shown").clicked() {
+                        copy = true;
+                    }
+                    if ui.button("🔄").on_hover_text("Rebuild from the current selection").clicked() {
+                        refresh = true;
+                    }
+                });
+            });
+            ui.separator();
+
+            // --- File jump links ---
+            egui::SidePanel::left("export_preview_files")
+                .resizable(true)
+                .default_width(200.0)
+                .show_inside(ui, |ui| {
+                    let mut jump = None;
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for (rel_path, offset) in &preview.document.files {
+                            if ui.link(rel_path).clicked() {
+                                // Banners open with two newlines; land on the "=====" line
+                                jump = Some(preview.line_of_offset(*offset) + 2);
+                            }
+                        }
+                    });
+                    if jump.is_some() {
+                        preview.scroll_to_line = jump;
+                    }
+                });
+
+            // --- Assembled text ---
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            let mut scroll = egui::ScrollArea::both().auto_shrink(false);
+            if let Some(line) = preview.scroll_to_line.take() {
+                scroll = scroll.vertical_scroll_offset(line as f32 * (row_height + ui.spacing().item_spacing.y));
+            }
+            scroll.show_rows(ui, row_height, preview.line_starts.len(), |ui, rows| {
+                for idx in rows {
+                    ui.label(egui::RichText::new(preview.line(idx)).monospace());
+                }
+            });
+        });
+
+    if copy {
+        // Same bookkeeping as any other copy: change-tracking baseline and history
+        if let (Some(preview), Some(root)) = (app.export_preview.take(), &app.root_node) {
+            let snapshot = tracking::snapshot(root, preview.document.hashes.clone());
+            app.copy_document(&preview.document, snapshot);
+            app.export_preview = Some(preview);
+        }
+    }
+    if !open {
+        app.export_preview = None;
+    } else if refresh {
+        app.open_export_preview();
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::OnceLock;
+use eframe::egui;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+// Above this size the preview falls back to plain text
+const MAX_HIGHLIGHT_BYTES: usize = 2 * 1024 * 1024;
+
+// A highlighted line: colored fragments in order
+pub type StyledLine = Vec<(egui::Color32, String)>;
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn themes() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+// The bundled syntaxes lack a few of the languages we color in the tree
+fn syntax_extension(extension: &str) -> &str {
+    match extension {
+        "ts" | "tsx" | "jsx" | "mjs" | "cjs" => "js",
+        "blade.php" => "php",
+        "vue" | "svelte" => "html",
+        "scss" | "sass" | "less" => "css",
+        other => other,
+    }
+}
+
+// Heavy work: meant to run on the preview loader thread
+pub fn highlight_lines(code: &str, extension: &str, dark_mode: bool) -> Option<Vec<StyledLine>> {
+    if code.len() > MAX_HIGHLIGHT_BYTES {
+        return None;
+    }
+    let syntaxes = syntaxes();
+    let syntax = syntaxes.find_syntax_by_extension(syntax_extension(extension))?;
+    let theme = &themes().themes[if dark_mode { "base16-ocean.dark" } else { "InspiredGitHub" }];
+    let mut highlighter = HighlightLines::new(syntax, theme);
+
+    let mut lines = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        let regions = highlighter.highlight_line(line, syntaxes).ok()?;
+        lines.push(regions.into_iter()
+            .map(|(style, text)| {
+                let fg = style.foreground;
+                (egui::Color32::from_rgb(fg.r, fg.g, fg.b), text.trim_end_matches(['\n', '\r']).to_string())
+            })
+            .collect());
+    }
+    Some(lines)
+}
+
+pub fn layout_line(line: &StyledLine, font_id: &egui::FontId) -> egui::text::LayoutJob {
+    let mut job = egui::text::LayoutJob::default();
+    for (color, text) in line {
+        job.append(text, 0.0, egui::TextFormat::simple(font_id.clone(), *color));
+    }
+    job
+}
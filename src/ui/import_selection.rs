@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::Path;
+use eframe::egui;
+use crate::app::CodeCollectorApp;
+use crate::models::dir_node::DirNode;
+use crate::models::line_range::LineRange;
+use crate::models::profile::{ApplyMode, SelectionEntry};
+use crate::operations::{content, response, selection};
+use crate::operations::response::BlockTarget;
+use super::apply_response::{line_diff, show_diff_rows};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportStatus {
+    Same,
+    Differs,
+    Partial,         // Exported with omissions (truncated, line ranges, skeleton) or as a diff; not compared
+    Missing(String), // Not in the open project, or unusable path
+}
+
+// One file listed in the imported export
+pub struct ImportedFile {
+    pub path: String,             // As written in the export
+    pub rel_path: Option<String>, // Matching project file
+    pub ranges: Vec<LineRange>,   // Exported line ranges; empty = the whole file
+    pub status: ImportStatus,
+    pub diff: Vec<(char, String)>, // Exported -> local
+    pub added: usize,
+    pub removed: usize,
+}
+
+pub struct ImportSelectionDialog {
+    pub source: String, // File name of the imported export
+    pub files: Vec<ImportedFile>,
+    pub current: usize,
+}
+
+impl ImportSelectionDialog {
+    pub fn load(root: &DirNode, path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+        let source = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().to_string();
+        let files: Vec<ImportedFile> = response::parse_response(&text).into_iter().map(|block| check_file(root, block)).collect();
+        if files.is_empty() {
+            return Err(format!("No exported files found in {}", source));
+        }
+        Ok(Self { source, files, current: 0 })
+    }
+
+    fn entries(&self) -> Vec<SelectionEntry> {
+        self.files.iter()
+            .filter_map(|f| f.rel_path.clone().map(|rel_path| SelectionEntry { rel_path, ranges: f.ranges.clone() }))
+            .collect()
+    }
+}
+
+fn check_file(root: &DirNode, block: response::ResponseBlock) -> ImportedFile {
+    let mut file = ImportedFile {
+        path: block.path.clone(),
+        rel_path: None,
+        ranges: response::exported_ranges(&block.content),
+        status: ImportStatus::Same,
+        diff: Vec::new(),
+        added: 0,
+        removed: 0,
+    };
+    let rel_path = match response::resolve_target(root, &block.path) {
+        BlockTarget::Existing(rel_path) => rel_path,
+        BlockTarget::New(rel_path) => {
+            file.status = ImportStatus::Missing(format!("{} is not in this project", rel_path));
+            return file;
+        }
+        BlockTarget::Invalid(reason) => {
+            file.status = ImportStatus::Missing(reason);
+            return file;
+        }
+    };
+
+    let local = selection::find_file_by_rel_path(root, &rel_path).and_then(|f| content::read_string(f).ok());
+    file.rel_path = Some(rel_path);
+    file.status = match local {
+        _ if block.partial || response::looks_partial(&block.content) => ImportStatus::Partial,
+        Some(local) if trim_blank_edges(&local) == trim_blank_edges(&block.content) => ImportStatus::Same,
+        Some(local) => {
+            (file.diff, file.added, file.removed) = line_diff(&block.content, &local.replace("\r\n", "\n"));
+            ImportStatus::Differs
+        }
+        None => ImportStatus::Partial,
+    };
+    file
+}
+
+// Exports drop blank lines at the start and end of a file
+fn trim_blank_edges(text: &str) -> String {
+    let text = text.replace("\r\n", "\n");
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines.iter().position(|l| !l.trim().is_empty()).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|l| !l.trim().is_empty()).map(|i| i + 1).unwrap_or(start);
+    lines[start..end].iter().map(|l| l.trim_end()).collect::<Vec<_>>().join("\n")
+}
+
+fn status_label(status: &ImportStatus) -> (String, egui::Color32) {
+    match status {
+        ImportStatus::Same => ("same as local".to_string(), egui::Color32::from_rgb(52, 199, 89)),
+        ImportStatus::Differs => ("local differs".to_string(), egui::Color32::from_rgb(255, 149, 0)),
+        ImportStatus::Partial => ("partial export, not compared".to_string(), egui::Color32::GRAY),
+        ImportStatus::Missing(reason) => (reason.clone(), egui::Color32::from_rgb(255, 59, 48)),
+    }
+}
+
+pub fn show_import_selection_window(ctx: &egui::Context, app: &mut CodeCollectorApp) {
+    let Some(dialog) = &mut app.import_dialog else { return; };
+
+    let mut open = true;
+    let mut apply = None;
+
+    egui::Window::new(format!("📂 Import Selection — {}", dialog.source))
+        .open(&mut open)
+        .default_size([900.0, 600.0])
+        .resizable(true)
+        .show(ctx, |ui| {
+            let found = dialog.files.iter().filter(|f| f.rel_path.is_some()).count();
+            let differs = dialog.files.iter().filter(|f| f.status == ImportStatus::Differs).count();
+            let missing = dialog.files.len() - found;
+
+            egui::SidePanel::left("import_selection_files")
+                .resizable(true)
+                .default_width(280.0)
+                .show_inside(ui, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for (idx, file) in dialog.files.iter().enumerate() {
+                            let label = file.rel_path.clone().unwrap_or_else(|| file.path.clone());
+                            if ui.selectable_label(dialog.current == idx, egui::RichText::new(label).monospace()).clicked() {
+                                dialog.current = idx;
+                            }
+                            ui.horizontal(|ui| {
+                                ui.add_space(12.0);
+                                let (text, color) = status_label(&file.status);
+                                ui.label(egui::RichText::new(text).small().color(color));
+                                if file.status == ImportStatus::Differs {
+                                    ui.label(egui::RichText::new(format!("+{} −{}", file.added, file.removed)).small().weak());
+                                }
+                                if !file.ranges.is_empty() {
+                                    let ranges = file.ranges.iter().map(|r| format!("{}-{}", r.start, r.end)).collect::<Vec<_>>().join(", ");
+                                    ui.label(egui::RichText::new(format!("lines {}", ranges)).small().weak());
+                                }
+                            });
+                        }
+                    });
+                });
+
+            ui.horizontal(|ui| {
+                ui.label(format!("{} files found, {} differ, {} missing", found, differs, missing));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.add_enabled_ui(found > 0, |ui| {
+                        if ui.button("Add to selection").clicked() {
+                            apply = Some(ApplyMode::Union);
+                        }
+                        if ui.button("✔ Replace selection").clicked() {
+                            apply = Some(ApplyMode::Replace);
+                        }
+                    });
+                });
+            });
+            ui.separator();
+
+            let Some(file) = dialog.files.get(dialog.current) else { return; };
+            match &file.status {
+                ImportStatus::Differs => {
+                    ui.label(egui::RichText::new("− exported   + local now").small().weak())
+                        .on_hover_text("Export options such as comment stripping also show up as differences");
+                    show_diff_rows(ui, &file.diff);
+                }
+                status => {
+                    let (text, color) = status_label(status);
+                    ui.colored_label(color, text);
+                }
+            }
+        });
+
+    if let Some(mode) = apply {
+        let entries = dialog.entries();
+        let source = dialog.source.clone();
+        if let Some(root) = &mut app.root_node {
+            selection::apply_entries(root, &entries, mode);
+            app.status_text = format!("Selected {} files from {}", entries.len(), source);
+            app.invalidate_token_estimate();
+        }
+        app.import_dialog = None;
+    } else if !open {
+        app.import_dialog = None;
+    }
+}
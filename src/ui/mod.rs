@@ -1,3 +1,12 @@
 pub mod styles;
 pub mod tree;
-pub mod panels;
\ No newline at end of file
+pub mod panels;
+pub mod preview;
+pub mod highlight;
+pub mod export_preview;
+pub mod recents;
+pub mod pattern_dialog;
+pub mod apply_response;
+pub mod patch_dialog;
+pub mod import_selection;
+pub mod content_search;
@@ -1,10 +1,14 @@
 use eframe::egui;
 use crate::app::CodeCollectorApp;
 use crate::models::export_mode::ExportMode;
+use crate::models::export_record::ExportTarget;
+use crate::models::export_options::{ConflictPolicy, LineNumberStyle, TruncationLimit};
+use crate::models::profile::ApplyMode;
 use crate::models::theme::ThemePreference;
-use crate::operations::export;
+use crate::operations::{export, selection};
 use super::styles::get_file_color;
 use super::tree::render_selected_list;
+use super::recents;
 
 pub fn show_top_panel(ctx: &egui::Context, app: &mut CodeCollectorApp) {
     egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
@@ -16,14 +20,44 @@ pub fn show_top_panel(ctx: &egui::Context, app: &mut CodeCollectorApp) {
                 if ui.add_enabled(!app.is_loading, egui::Button::new("Open Project")).clicked() {
                     app.open_folder_dialog();
                 }
+                ui.menu_button("🕒", |ui| {
+                    ui.set_min_width(300.0);
+                    recents::show_recent_projects_list(ui, app);
+                }).response.on_hover_text("Recent Projects");
                 ui.add_space(5.0);
 
                 // --- Refresh Button ---
                 // Only show if a project is loaded
+                if app.project_path.is_some()
+                    && ui.add_enabled(!app.is_loading, egui::Button::new("🔄")).on_hover_text("Refresh Folder").clicked()
+                {
+                    app.refresh_project();
+                }
+
+                // --- Git revision ---
                 if app.project_path.is_some() {
-                    if ui.add_enabled(!app.is_loading, egui::Button::new("🔄")).on_hover_text("Refresh Folder").clicked() {
-                        app.refresh_project();
-                    }
+                    let label = match &app.revision {
+                        Some(rev) => format!("⎇ {}", rev),
+                        None => "⎇".to_string(),
+                    };
+                    ui.add_enabled_ui(!app.is_loading, |ui| {
+                        ui.menu_button(label, |ui| {
+                            ui.label("Open the project as of a branch, tag or commit:");
+                            ui.horizontal(|ui| {
+                                let input = ui.add(egui::TextEdit::singleline(&mut app.revision_input).hint_text("v1.0.0").desired_width(160.0));
+                                let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                                if ui.button("Open").clicked() || submitted {
+                                    let rev = app.revision_input.clone();
+                                    app.open_at_revision(&rev);
+                                    ui.close_menu();
+                                }
+                            });
+                            if app.revision.is_some() && ui.button("Back to working tree").clicked() {
+                                app.open_working_tree();
+                                ui.close_menu();
+                            }
+                        }).response.on_hover_text("Git revision");
+                    });
                 }
 
                 ui.add_space(10.0);
@@ -47,7 +81,14 @@ pub fn show_top_panel(ctx: &egui::Context, app: &mut CodeCollectorApp) {
             ui.separator();
             ui.horizontal(|ui| {
                 ui.label("🔍");
-                ui.add(egui::TextEdit::singleline(&mut app.search_query).hint_text("Search files...").desired_width(f32::INFINITY));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("🔎 In files…").on_hover_text("Search file contents").clicked() {
+                        app.content_search.get_or_insert_with(Default::default);
+                    }
+                    ui.toggle_value(&mut app.ranked_view, "≡ Ranked")
+                        .on_hover_text("Show matches as a flat list, best first, instead of the filtered tree");
+                    ui.add(egui::TextEdit::singleline(&mut app.search_query).hint_text("Search paths (fuzzy)...").desired_width(f32::INFINITY));
+                });
             });
         }
         ui.add_space(5.0);
@@ -60,6 +101,47 @@ pub fn show_bottom_panel(ctx: &egui::Context, app: &mut CodeCollectorApp) {
         ui.horizontal(|ui| {
             if ui.button("Select All").clicked() { app.select_all(true); }
             if ui.button("None").clicked() { app.select_all(false); }
+            if ui.add_enabled(app.root_node.is_some(), egui::Button::new("Pattern…")).on_hover_text("Select by extension, glob or regex").clicked() {
+                app.pattern_dialog.get_or_insert_with(Default::default);
+            }
+            if ui.add_enabled(app.root_node.is_some(), egui::Button::new("📂 Import Selection…"))
+                .on_hover_text("Select the files listed in an exported full_code.txt")
+                .clicked()
+            {
+                app.open_import_selection();
+            }
+            if ui.add_enabled(app.root_node.is_some() && app.revision.is_none(), egui::Button::new("📥 Apply Response…"))
+                .on_hover_text("Write files from a model's reply back to disk")
+                .clicked()
+            {
+                app.apply_dialog.get_or_insert_with(Default::default);
+            }
+            if ui.add_enabled(app.root_node.is_some() && app.revision.is_none(), egui::Button::new("🩹 Apply Patch…"))
+                .on_hover_text("Apply unified diffs hunk by hunk")
+                .clicked()
+            {
+                app.patch_dialog.get_or_insert_with(Default::default);
+            }
+            if app.last_snapshot.is_some() && ui.button("↶ Undo Apply").on_hover_text("Restore the files changed by the last apply").clicked() {
+                app.undo_last_apply();
+            }
+            ui.add_enabled_ui(app.root_node.is_some(), |ui| {
+                ui.menu_button("Changed…", |ui| {
+                    if ui.button("Modified, staged and untracked files").clicked() {
+                        app.select_working_tree_changes();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Since");
+                        ui.add(egui::TextEdit::singleline(&mut app.git_base_ref).hint_text("main").desired_width(120.0));
+                        if ui.button("Select").on_hover_text("Everything changed since the merge base with this ref, including uncommitted work").clicked() {
+                            app.select_changed_since();
+                            ui.close_menu();
+                        }
+                    });
+                });
+            });
             ui.separator();
             ui.label(&app.status_text);
         });
@@ -69,10 +151,31 @@ pub fn show_bottom_panel(ctx: &egui::Context, app: &mut CodeCollectorApp) {
             ui.radio_value(&mut app.export_mode, ExportMode::OneFile, "Single File (.txt)");
             ui.radio_value(&mut app.export_mode, ExportMode::SeparateFiles, "Separate Files");
         });
+        if app.export_mode == ExportMode::SeparateFiles {
+            ui.horizontal(|ui| {
+                let opts = &mut app.separate_options;
+                ui.label("On conflict:");
+                egui::ComboBox::from_id_salt("conflict_policy")
+                    .selected_text(opts.conflict_policy.label())
+                    .show_ui(ui, |ui| {
+                        for policy in [ConflictPolicy::Overwrite, ConflictPolicy::Skip, ConflictPolicy::Rename] {
+                            ui.selectable_value(&mut opts.conflict_policy, policy, policy.label());
+                        }
+                    });
+                ui.checkbox(&mut opts.clean_target, "Clean target first");
+                ui.checkbox(&mut opts.flatten, "Flatten paths").on_hover_text("src/ui/tree.rs → src__ui__tree.rs");
+                ui.checkbox(&mut opts.preserve_metadata, "Keep timestamps");
+                ui.checkbox(&mut opts.write_manifest, "Write manifest");
+            });
+        }
         ui.add_space(5.0);
         ui.horizontal_centered(|ui| {
-            let w = (ui.available_width() / 2.0) - 5.0;
+            let w = (ui.available_width() / 3.0) - 6.0;
             let copy_enabled = app.export_mode == ExportMode::OneFile && !app.is_loading;
+            if ui.add_enabled_ui(copy_enabled, |ui| {
+                ui.add_sized([w, 40.0], egui::Button::new("👁 Preview Export"))
+            }).inner.clicked() { app.open_export_preview(); }
+
             if ui.add_enabled_ui(copy_enabled, |ui| {
                 ui.add_sized([w, 40.0], egui::Button::new("📋 Copy to Clipboard"))
             }).inner.clicked() { app.copy_to_clipboard(); }
@@ -109,12 +212,112 @@ pub fn show_side_panel(ctx: &egui::Context, app: &mut CodeCollectorApp) {
             ui.separator();
             egui::CollapsingHeader::new("✅ Selected Files").default_open(true).show(ui, |ui| {
                 egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
-                    if let Some(root) = &mut app.root_node {
-                        render_selected_list(ui, root);
+                    if app.root_node.as_mut().is_some_and(|root| render_selected_list(ui, root)) {
+                        app.invalidate_token_estimate();
                     }
                 });
             });
 
+            ui.separator();
+            egui::CollapsingHeader::new("🔗 Used By").default_open(false).show(ui, |ui| {
+                show_used_by_section(ctx, ui, app);
+            });
+
+            ui.separator();
+            egui::CollapsingHeader::new("Δ Since Last Export").default_open(false).show(ui, |ui| {
+                show_since_export_section(ui, app);
+            });
+
+            ui.separator();
+            egui::CollapsingHeader::new("📜 Export History").default_open(false).show(ui, |ui| {
+                show_history_section(ui, app);
+            });
+
+            ui.separator();
+            egui::CollapsingHeader::new("🗂 Selection Profiles").default_open(false).show(ui, |ui| {
+                show_profiles_section(ui, app);
+            });
+
+            ui.separator();
+            let options_before = app.export_options.clone();
+            let mut refs_committed = false;
+            egui::CollapsingHeader::new("⚙ Export Options").default_open(false).show(ui, |ui| {
+                let transform = &mut app.export_options.transform;
+                ui.checkbox(&mut transform.strip_comments, "Strip comments");
+                ui.checkbox(&mut transform.strip_license, "Strip license headers");
+                ui.checkbox(&mut transform.collapse_blank_lines, "Collapse blank lines");
+                ui.checkbox(&mut transform.trim_trailing_whitespace, "Trim trailing whitespace");
+                ui.checkbox(&mut app.export_options.skeleton, "Signatures only (skeleton)")
+                    .on_hover_text("Rust, TS/JS, Python, Java, Go, C/C++, PHP: keep declarations, signatures and docs; elide bodies.\nRight-click a file to set this per file.");
+
+                ui.add_space(5.0);
+                let truncation = &mut app.export_options.truncation;
+                ui.checkbox(&mut truncation.enabled, "Truncate large files");
+                ui.add_enabled_ui(truncation.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Max");
+                        ui.add(egui::DragValue::new(&mut truncation.max).range(1..=1_000_000));
+                        ui.selectable_value(&mut truncation.limit, TruncationLimit::Lines, "lines");
+                        ui.selectable_value(&mut truncation.limit, TruncationLimit::Tokens, "tokens");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Keep first");
+                        ui.add(egui::DragValue::new(&mut truncation.head_lines).range(0..=100_000));
+                        ui.label("last");
+                        ui.add(egui::DragValue::new(&mut truncation.tail_lines).range(0..=100_000));
+                    });
+                });
+
+                ui.add_space(5.0);
+                let diff = &mut app.export_options.diff;
+                ui.checkbox(&mut diff.enabled, "Diff only (git)")
+                    .on_hover_text("Export unified diffs of the selected files instead of their full text");
+                ui.add_enabled_ui(diff.enabled, |ui| {
+                    egui::Grid::new("diff_options").num_columns(2).show(ui, |ui| {
+                        ui.label("Base");
+                        refs_committed |= ui.add(egui::TextEdit::singleline(&mut diff.base_ref).hint_text("main").desired_width(120.0))
+                            .on_hover_text("Diffs start where the target branched off this ref (like git diff base...target)")
+                            .lost_focus();
+                        ui.end_row();
+                        ui.label("Target");
+                        refs_committed |= ui.add(egui::TextEdit::singleline(&mut diff.target_ref).hint_text("working tree").desired_width(120.0))
+                            .lost_focus();
+                        ui.end_row();
+                        ui.label("Context");
+                        ui.add(egui::DragValue::new(&mut diff.context_lines).range(0..=100).suffix(" lines"));
+                        ui.end_row();
+                    });
+                    ui.checkbox(&mut diff.include_full_file, "Append full new version");
+                });
+
+                ui.add_space(5.0);
+                let numbers = &mut app.export_options.line_numbers;
+                ui.checkbox(&mut numbers.enabled, "Line numbers")
+                    .on_hover_text("Numbers refer to the original file, even when lines are stripped");
+                ui.add_enabled_ui(numbers.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("line_number_style")
+                            .selected_text(numbers.style.label())
+                            .show_ui(ui, |ui| {
+                                for style in [LineNumberStyle::Pipe, LineNumberStyle::Colon, LineNumberStyle::Tab] {
+                                    ui.selectable_value(&mut numbers.style, style, style.label());
+                                }
+                            });
+                        ui.label("Width:");
+                        ui.add(egui::DragValue::new(&mut numbers.width).range(0..=8))
+                            .on_hover_text("Minimum digits (0 = auto)");
+                    });
+                });
+            });
+
+            // Every estimate runs git per file, so the ref fields only count once they lose focus
+            let mut compared = options_before;
+            compared.diff.base_ref.clone_from(&app.export_options.diff.base_ref);
+            compared.diff.target_ref.clone_from(&app.export_options.diff.target_ref);
+            if refs_committed || app.export_options != compared {
+                app.invalidate_token_estimate();
+            }
+
             ui.separator();
             egui::CollapsingHeader::new("📊 Selection Stats").default_open(true).show(ui, |ui| {
                 if let Some(root) = &app.root_node {
@@ -124,7 +327,226 @@ pub fn show_side_panel(ctx: &egui::Context, app: &mut CodeCollectorApp) {
                     ui.label(format!("Files: {}", count));
                     ui.label(format!("Size: {:.2} KB", kb));
                     ui.label(format!("Est. Lines: ~{}", est_lines));
+                    match app.token_estimate {
+                        Some(tokens) => ui.label(format!("Est. Tokens: ~{}", tokens)),
+                        None => ui.label("Est. Tokens: …"),
+                    };
+                    show_missing_context(ui, app);
                 }
             });
         });
-}
\ No newline at end of file
+}
+
+// What changed on disk since the last copy/save
+fn show_since_export_section(ui: &mut egui::Ui, app: &mut CodeCollectorApp) {
+    let Some(snapshot) = &app.last_export else {
+        ui.label(egui::RichText::new("Nothing exported from this project yet").italics().weak());
+        return;
+    };
+    ui.label(egui::RichText::new(format!("{} files, {}", snapshot.files.len(), recents::format_age(snapshot.exported_at))).weak());
+
+    let changes = &app.export_changes;
+    if app.tracking_loader.is_some() {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label(egui::RichText::new("Checking for changes…").weak());
+        });
+    } else if changes.is_empty() {
+        ui.label(egui::RichText::new("No changes (refresh to re-check)").italics().weak());
+    } else {
+        ui.label(format!("Modified: {}", changes.modified.len()));
+        if !changes.added.is_empty() {
+            let mut added: Vec<&str> = changes.added.iter().map(String::as_str).collect();
+            added.sort();
+            ui.label(format!("Added: {}", added.len())).on_hover_text(added.join("\n"));
+        }
+        if !changes.deleted.is_empty() {
+            ui.label(format!("Deleted: {}", changes.deleted.len()))
+                .on_hover_text(changes.deleted.join("\n"));
+        }
+    }
+    let any_modified = !changes.modified.is_empty() || !changes.added.is_empty();
+    ui.horizontal(|ui| {
+        if ui.add_enabled(any_modified, egui::Button::new("Select changed")).clicked() {
+            app.select_changed_since_export();
+        }
+        if ui.add_enabled(app.delta_loader.is_none(), egui::Button::new("📋 Copy delta"))
+            .on_hover_text("Changed and added files, newly selected files and a list of deleted ones")
+            .clicked()
+        {
+            app.copy_export_delta(ui.ctx());
+        }
+    });
+}
+
+fn show_history_section(ui: &mut egui::Ui, app: &mut CodeCollectorApp) {
+    if app.history.is_empty() {
+        ui.label(egui::RichText::new("No exports from this project yet").italics().weak());
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut app.history_profile_name).hint_text("Profile name").desired_width(120.0))
+            .on_hover_text("Name used by \"→ Profile\"");
+    });
+    let can_name = !app.history_profile_name.trim().is_empty();
+    let mut rerun = None;
+    let mut to_profile = None;
+    egui::ScrollArea::vertical().id_salt("export_history").max_height(250.0).show(ui, |ui| {
+        for (idx, record) in app.history.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(record.target.icon()).on_hover_text(record.target.label());
+                ui.label(recents::format_age(record.exported_at));
+                ui.label(egui::RichText::new(format!("{} files · ~{} tokens", record.file_count, record.tokens)).small().weak())
+                    .on_hover_text(record.entries.iter().map(|e| e.rel_path.as_str()).collect::<Vec<_>>().join("\n"));
+            });
+            ui.horizontal(|ui| {
+                ui.add_space(16.0);
+                if ui.small_button("↻ Re-run").on_hover_text(format!("Select these files again and export to {} with the same options", record.target.label())).clicked() {
+                    rerun = Some(idx);
+                }
+                if let ExportTarget::File(path) | ExportTarget::Folder(path) = &record.target {
+                    if ui.add_enabled(path.exists(), egui::Button::new("Open").small()).clicked() {
+                        let _ = open::that(path);
+                    }
+                }
+                if ui.add_enabled(can_name, egui::Button::new("→ Profile").small()).clicked() {
+                    to_profile = Some(idx);
+                }
+            });
+        }
+    });
+
+    if let Some(idx) = rerun {
+        app.pending_rerun = Some(idx);
+    }
+    if let Some(idx) = to_profile {
+        app.history_to_profile(idx);
+    }
+}
+
+// Files importing the previewed file, from the import graph
+fn show_used_by_section(ctx: &egui::Context, ui: &mut egui::Ui, app: &mut CodeCollectorApp) {
+    let Some(rel_path) = app.preview.as_ref().map(|p| selection::normalize_rel_path(&p.rel_path)) else {
+        ui.label(egui::RichText::new("Preview a file to see which files import it").italics().weak());
+        return;
+    };
+    let Some(graph) = &app.import_graph else {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label(egui::RichText::new("Indexing imports…").weak());
+        });
+        return;
+    };
+
+    let users = graph.importers_of(&rel_path).to_vec();
+    if users.is_empty() {
+        ui.label(egui::RichText::new("No project file imports this one").italics().weak());
+        return;
+    }
+
+    let mut open = None;
+    egui::ScrollArea::vertical().id_salt("used_by").max_height(200.0).show(ui, |ui| {
+        for user in &users {
+            if ui.link(user).on_hover_text("Preview").clicked() {
+                open = Some(user.clone());
+            }
+        }
+    });
+    ui.horizontal(|ui| {
+        if ui.button("Select these").clicked() {
+            app.select_dependents(&rel_path, Some(1));
+        }
+        if ui.button("Select all (transitive)").clicked() {
+            app.select_dependents(&rel_path, None);
+        }
+    });
+
+    let path = open.and_then(|user| {
+        app.root_node.as_ref().and_then(|root| selection::find_file_by_rel_path(root, &user)).map(|f| f.path.clone())
+    });
+    if let Some(path) = path {
+        app.open_preview(ctx, &path);
+    }
+}
+
+// Selected files whose local imports are left out of the export
+fn show_missing_context(ui: &mut egui::Ui, app: &mut CodeCollectorApp) {
+    let missing = app.missing_context();
+    if missing.is_empty() {
+        return;
+    }
+
+    let warning = egui::Color32::from_rgb(255, 149, 0);
+    let mut add = None;
+    ui.add_space(5.0);
+    egui::CollapsingHeader::new(egui::RichText::new(format!("⚠ {} files import unselected files", missing.len())).color(warning))
+        .id_salt("missing_context")
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical().id_salt("missing_context_list").max_height(200.0).show(ui, |ui| {
+                for (rel_path, deps) in &missing {
+                    ui.horizontal(|ui| {
+                        if ui.small_button(format!("+{}", deps.len())).on_hover_text(deps.join("\n")).clicked() {
+                            add = Some(deps.clone());
+                        }
+                        ui.label(egui::RichText::new(rel_path).monospace().small());
+                    });
+                }
+            });
+            if ui.button("Add all missing").clicked() {
+                let mut all: Vec<String> = missing.iter().flat_map(|(_, deps)| deps.iter().cloned()).collect();
+                all.sort();
+                all.dedup();
+                add = Some(all);
+            }
+        });
+
+    if let Some(deps) = add {
+        app.add_missing_imports(&deps);
+    }
+}
+
+fn show_profiles_section(ui: &mut egui::Ui, app: &mut CodeCollectorApp) {
+    if app.root_node.is_none() {
+        ui.label(egui::RichText::new("Open a project first").italics().weak());
+        return;
+    }
+
+    let selected_name = app.active_profile
+        .and_then(|idx| app.profiles.get(idx))
+        .map(|p| format!("{} ({})", p.name, p.entries.len()))
+        .unwrap_or_else(|| "Choose profile…".to_string());
+    egui::ComboBox::from_id_salt("profile_selector")
+        .selected_text(selected_name)
+        .width(ui.available_width() - 10.0)
+        .show_ui(ui, |ui| {
+            for (idx, profile) in app.profiles.iter().enumerate() {
+                ui.selectable_value(&mut app.active_profile, Some(idx), format!("{} ({})", profile.name, profile.entries.len()));
+            }
+        });
+
+    ui.add_enabled_ui(app.active_profile.is_some(), |ui| {
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Apply").on_hover_text("Replace the current selection").clicked() {
+                app.apply_active_profile(ApplyMode::Replace);
+            }
+            if ui.button("Union").on_hover_text("Add to the current selection").clicked() {
+                app.apply_active_profile(ApplyMode::Union);
+            }
+            if ui.button("Overwrite").on_hover_text("Replace the profile with the current selection").clicked() {
+                app.overwrite_active_profile();
+            }
+            if ui.button("🗑").on_hover_text("Delete profile").clicked() {
+                app.delete_active_profile();
+            }
+        });
+    });
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut app.new_profile_name).hint_text("New profile name").desired_width(120.0));
+        if ui.add_enabled(!app.new_profile_name.trim().is_empty(), egui::Button::new("Save")).clicked() {
+            let name = std::mem::take(&mut app.new_profile_name);
+            app.save_profile_as(&name);
+        }
+    });
+}
@@ -0,0 +1,200 @@
+use eframe::egui;
+use crate::app::CodeCollectorApp;
+use crate::models::dir_node::DirNode;
+use crate::operations::{content, patch, response, selection};
+use crate::operations::patch::{FilePatch, HunkFit, HunkLine};
+use crate::operations::response::BlockTarget;
+
+// One file of a parsed patch, checked against the project
+pub struct PatchFile {
+    pub patch: FilePatch,
+    pub rel_path: Option<String>, // None when the target cannot be used (see `error`)
+    pub original: String,
+    pub error: Option<String>,
+    pub accepted: Vec<bool>, // Per hunk
+    pub fits: Vec<HunkFit>,  // Per hunk, each tried on its own against the current file
+}
+
+#[derive(Default)]
+pub struct PatchDialog {
+    pub input: String,
+    pub files: Option<Vec<PatchFile>>, // None = still pasting
+    pub current: usize,
+}
+
+impl PatchDialog {
+    fn parse(&mut self, root: &DirNode) {
+        let files = patch::parse_patches(&self.input).into_iter().map(|p| check_file(root, p)).collect();
+        self.files = Some(files);
+        self.current = 0;
+    }
+}
+
+fn check_file(root: &DirNode, file_patch: FilePatch) -> PatchFile {
+    let is_new = file_patch.old_path.is_none();
+    let (rel_path, original, error) = match response::resolve_target(root, file_patch.path()) {
+        BlockTarget::Existing(rel_path) if is_new => (None, String::new(), Some(format!("{} already exists", rel_path))),
+        BlockTarget::Existing(rel_path) => {
+            match selection::find_file_by_rel_path(root, &rel_path).map(content::read_string) {
+                Some(Ok(text)) => (Some(rel_path), text, None),
+                _ => (None, String::new(), Some(format!("Cannot read {}", rel_path))),
+            }
+        }
+        BlockTarget::New(rel_path) if is_new => (Some(rel_path), String::new(), None),
+        BlockTarget::New(rel_path) => (None, String::new(), Some(format!("{} is not in the project", rel_path))),
+        BlockTarget::Invalid(reason) => (None, String::new(), Some(reason)),
+    };
+
+    let lines: Vec<&str> = original.lines().collect();
+    let fits: Vec<HunkFit> = file_patch.hunks.iter().map(|h| patch::locate_hunk(&lines, h, 0)).collect();
+    let accepted = fits.iter().map(|fit| error.is_none() && *fit != HunkFit::Failed).collect();
+    PatchFile { patch: file_patch, rel_path, original, error, accepted, fits }
+}
+
+fn fit_label(fit: &HunkFit) -> (String, egui::Color32) {
+    match fit {
+        HunkFit::Exact { line } => (format!("applies at line {}", line), egui::Color32::from_rgb(52, 199, 89)),
+        HunkFit::Offset { line, offset } => (format!("applies at line {} (offset {:+})", line, offset), egui::Color32::from_rgb(52, 199, 89)),
+        HunkFit::Fuzzy { line, fuzz } => (format!("applies at line {} with fuzz {}", line, fuzz), egui::Color32::from_rgb(255, 149, 0)),
+        HunkFit::Failed => ("does not apply".to_string(), egui::Color32::from_rgb(255, 59, 48)),
+    }
+}
+
+pub fn show_patch_dialog(ctx: &egui::Context, app: &mut CodeCollectorApp) {
+    let Some(dialog) = &mut app.patch_dialog else { return; };
+    let Some(root) = &app.root_node else { return; };
+
+    let mut open = true;
+    let mut back = false;
+    let mut apply = None;
+
+    egui::Window::new("🩹 Apply Patch")
+        .open(&mut open)
+        .default_size([900.0, 600.0])
+        .resizable(true)
+        .show(ctx, |ui| {
+            let Some(files) = &mut dialog.files else {
+                ui.label("Paste one or more unified diffs (text around them is ignored).");
+                egui::ScrollArea::vertical().max_height(ui.available_height() - 40.0).show(ui, |ui| {
+                    ui.add(egui::TextEdit::multiline(&mut dialog.input)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(25));
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("📋 Paste").clicked() {
+                        if let Ok(text) = arboard::Clipboard::new().and_then(|mut c| c.get_text()) {
+                            dialog.input = text;
+                        }
+                    }
+                    if ui.add_enabled(!dialog.input.trim().is_empty(), egui::Button::new("Parse")).clicked() {
+                        dialog.parse(root);
+                    }
+                });
+                return;
+            };
+
+            if files.is_empty() {
+                ui.colored_label(egui::Color32::from_rgb(255, 149, 0), "No unified diff found in the pasted text.");
+                if ui.button("Back").clicked() {
+                    back = true;
+                }
+                return;
+            }
+
+            // --- Files ---
+            egui::SidePanel::left("patch_files")
+                .resizable(true)
+                .default_width(260.0)
+                .show_inside(ui, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for (idx, file) in files.iter().enumerate() {
+                            let accepted = file.accepted.iter().filter(|a| **a).count();
+                            let label = format!("{} ({}/{})", file.patch.path(), accepted, file.accepted.len());
+                            if ui.selectable_label(dialog.current == idx, egui::RichText::new(label).monospace()).clicked() {
+                                dialog.current = idx;
+                            }
+                            if let Some(error) = &file.error {
+                                ui.label(egui::RichText::new(error).small().color(egui::Color32::from_rgb(255, 59, 48)));
+                            }
+                        }
+                    });
+                });
+
+            let total: usize = files.iter().map(|f| f.accepted.iter().filter(|a| **a).count()).sum();
+            ui.horizontal(|ui| {
+                if ui.button("Back").clicked() {
+                    back = true;
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.add_enabled(total > 0, egui::Button::new(format!("✔ Apply {} hunks", total)))
+                        .on_hover_text("Originals are backed up; use Undo Apply to revert")
+                        .clicked()
+                    {
+                        apply = Some(build_writes(files));
+                    }
+                });
+            });
+            ui.separator();
+
+            // --- Hunks of the current file ---
+            let Some(file) = files.get_mut(dialog.current) else { return; };
+            let valid = file.error.is_none();
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                for (idx, hunk) in file.patch.hunks.iter().enumerate() {
+                    let (status, color) = fit_label(&file.fits[idx]);
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(valid, egui::Checkbox::new(&mut file.accepted[idx], egui::RichText::new(&hunk.header).monospace()));
+                        ui.label(egui::RichText::new(status).small().color(color));
+                    });
+                    for line in &hunk.lines {
+                        let (text, color) = match line {
+                            HunkLine::Add(t) => (format!("+{}", t), egui::Color32::from_rgb(52, 199, 89)),
+                            HunkLine::Remove(t) => (format!("-{}", t), egui::Color32::from_rgb(255, 59, 48)),
+                            HunkLine::Context(t) => (format!(" {}", t), ui.visuals().weak_text_color()),
+                        };
+                        ui.label(egui::RichText::new(text).monospace().color(color));
+                    }
+                    ui.separator();
+                }
+            });
+        });
+
+    if back {
+        if let Some(dialog) = &mut app.patch_dialog {
+            dialog.files = None;
+        }
+    }
+    if let Some((writes, failed)) = apply {
+        app.apply_patch_changes(&writes);
+        if failed > 0 {
+            app.status_text.push_str(&format!(" — {} hunks did not apply", failed));
+        }
+    }
+    if !open {
+        app.patch_dialog = None;
+    }
+}
+
+// New contents per file (None = delete) and the number of accepted hunks that failed
+fn build_writes(files: &[PatchFile]) -> (Vec<(String, Option<String>)>, usize) {
+    let mut writes = Vec::new();
+    let mut failed = 0;
+    for file in files {
+        let Some(rel_path) = &file.rel_path else { continue; };
+        let hunks: Vec<_> = file.patch.hunks.iter().zip(&file.accepted).filter(|(_, a)| **a).map(|(h, _)| h).collect();
+        if hunks.is_empty() {
+            continue;
+        }
+        let (text, fits) = patch::apply_hunks(&file.original, &hunks);
+        let failures = fits.iter().filter(|f| **f == HunkFit::Failed).count();
+        failed += failures;
+        if failures == hunks.len() {
+            continue;
+        }
+        // A deletion patch that applied completely removes the file
+        let deleted = file.patch.new_path.is_none() && failures == 0 && hunks.len() == file.patch.hunks.len() && text.trim().is_empty();
+        writes.push((rel_path.clone(), if deleted { None } else { Some(text) }));
+    }
+    (writes, failed)
+}
@@ -0,0 +1,80 @@
+use eframe::egui;
+use crate::app::CodeCollectorApp;
+use crate::operations::pattern::{self, PathMatcher, PatternAction, PatternKind};
+
+pub struct PatternDialog {
+    pub kind: PatternKind,
+    pub pattern: String,
+    pub action: PatternAction,
+    pub case_sensitive: bool,
+}
+
+impl Default for PatternDialog {
+    fn default() -> Self {
+        Self {
+            kind: PatternKind::Extensions,
+            pattern: String::new(),
+            action: PatternAction::Add,
+            case_sensitive: false,
+        }
+    }
+}
+
+pub fn show_pattern_dialog(ctx: &egui::Context, app: &mut CodeCollectorApp) {
+    let Some(dialog) = &mut app.pattern_dialog else { return; };
+    let Some(root) = &mut app.root_node else { return; };
+
+    let mut open = true;
+    let mut applied = None;
+
+    egui::Window::new("🎯 Select by Pattern")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.set_min_width(380.0);
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut dialog.kind, PatternKind::Extensions, "Extensions");
+                ui.selectable_value(&mut dialog.kind, PatternKind::Glob, "Glob");
+                ui.selectable_value(&mut dialog.kind, PatternKind::Regex, "Regex");
+            });
+
+            let hint = match dialog.kind {
+                PatternKind::Extensions => "rs, toml",
+                PatternKind::Glob => "src/**/handlers/*.ts",
+                PatternKind::Regex => r"^src/.*_test\.go$",
+            };
+            ui.add(egui::TextEdit::singleline(&mut dialog.pattern).hint_text(hint).desired_width(f32::INFINITY).font(egui::TextStyle::Monospace));
+            if dialog.kind != PatternKind::Extensions {
+                ui.checkbox(&mut dialog.case_sensitive, "Case sensitive");
+            }
+
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut dialog.action, PatternAction::Add, "Add to selection");
+                ui.radio_value(&mut dialog.action, PatternAction::Remove, "Remove");
+                ui.radio_value(&mut dialog.action, PatternAction::Replace, "Replace");
+            });
+            ui.separator();
+
+            let matcher = PathMatcher::compile(dialog.kind, &dialog.pattern, dialog.case_sensitive);
+            match &matcher {
+                Ok(m) => { ui.label(format!("Matches {} files", pattern::count_matches(root, m))); }
+                Err(e) => { ui.colored_label(egui::Color32::from_rgb(255, 149, 0), e); }
+            }
+
+            ui.add_space(5.0);
+            if let Ok(matcher) = matcher {
+                if ui.button("Apply").clicked() {
+                    applied = Some(pattern::apply_pattern(root, &matcher, dialog.action));
+                }
+            }
+        });
+
+    if let Some(changed) = applied {
+        app.status_text = format!("Pattern changed {} file(s)", changed);
+        app.invalidate_token_estimate();
+    }
+    if !open {
+        app.pattern_dialog = None;
+    }
+}
@@ -0,0 +1,205 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, channel};
+use std::thread;
+use eframe::egui;
+use crate::app::CodeCollectorApp;
+use crate::models::{file_node::FileNode, git_blob::GitBlob, line_range::{self, LineRange}};
+use crate::operations::{content, export, selection};
+use super::highlight::{self, StyledLine};
+
+pub struct PreviewContent {
+    pub lines: Vec<String>,
+    pub styled: Option<Vec<StyledLine>>, // None = plain text (unknown language or too large)
+    pub tokens: usize,
+}
+
+pub struct PreviewState {
+    pub path: PathBuf,
+    pub rel_path: String,
+    pub extension: String,
+    pub blob: Option<GitBlob>,
+    pub content: Option<Result<PreviewContent, String>>,
+    pub loader: Option<Receiver<Result<PreviewContent, String>>>,
+    pub dark_mode: bool,       // Colors of `content` were computed for this mode
+    pub anchor: Option<usize>, // First line of a range that is being picked
+    pub scroll_to: Option<usize>,   // 1-based line to bring into view once loaded
+    pub marked_line: Option<usize>, // Line a search result pointed at
+}
+
+impl PreviewState {
+    pub fn open(file: &FileNode, ctx: &egui::Context) -> Self {
+        let mut state = Self {
+            path: file.path.clone(),
+            rel_path: file.rel_path.clone(),
+            extension: file.extension.clone(),
+            blob: file.blob.clone(),
+            content: None,
+            loader: None,
+            dark_mode: ctx.style().visuals.dark_mode,
+            anchor: None,
+            scroll_to: None,
+            marked_line: None,
+        };
+        state.start_loading(ctx);
+        state
+    }
+
+    // Reads and highlights the file off the UI thread
+    fn start_loading(&mut self, ctx: &egui::Context) {
+        let (tx, rx) = channel();
+        self.loader = Some(rx);
+
+        let path = self.path.clone();
+        let blob = self.blob.clone();
+        let extension = self.extension.clone();
+        let dark_mode = self.dark_mode;
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let result = content::read_source(&path, blob.as_ref())
+                .and_then(|bytes| String::from_utf8(bytes).map_err(std::io::Error::other))
+                .map(|code| PreviewContent {
+                    styled: highlight::highlight_lines(&code, &extension, dark_mode),
+                    tokens: export::estimate_tokens(&code),
+                    lines: code.lines().map(str::to_string).collect(),
+                })
+                .map_err(|e| e.to_string());
+            let _ = tx.send(result);
+            ctx.request_repaint();
+        });
+    }
+
+    fn poll(&mut self, ctx: &egui::Context) {
+        if let Some(rx) = &self.loader {
+            if let Ok(result) = rx.try_recv() {
+                self.content = Some(result);
+                self.loader = None;
+            }
+        }
+
+        // Theme switched: recolor
+        let dark_mode = ctx.style().visuals.dark_mode;
+        if self.loader.is_none() && dark_mode != self.dark_mode {
+            self.dark_mode = dark_mode;
+            self.start_loading(ctx);
+        }
+    }
+}
+
+pub fn show_preview_panel(ui: &mut egui::Ui, app: &mut CodeCollectorApp) {
+    let Some(preview) = &mut app.preview else { return; };
+    let Some(file) = app.root_node.as_mut().and_then(|root| selection::find_file_mut(root, &preview.path)) else {
+        app.preview = None;
+        return;
+    };
+    preview.poll(ui.ctx());
+    if let Some(Ok(content)) = &preview.content {
+        file.line_count = Some(content.lines.len());
+    }
+    let before = (file.selected, file.line_ranges.clone());
+
+    let mut close = false;
+    ui.horizontal(|ui| {
+        let mut checked = file.selected;
+        if ui.checkbox(&mut checked, "").on_hover_text("Include in export").changed() {
+            file.set_selected(checked);
+        }
+        ui.label(egui::RichText::new(&preview.rel_path).strong().monospace());
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.button("✖").on_hover_text("Close preview").clicked() {
+                close = true;
+            }
+        });
+    });
+
+    // --- File info ---
+    let tokens = match &preview.content {
+        Some(Ok(content)) => format!("~{} tokens", content.tokens),
+        _ => "…".to_string(),
+    };
+    ui.label(egui::RichText::new(format!(
+        "{:.2} KB · {} lines · {}",
+        file.size_bytes as f64 / 1024.0, file.lines(), tokens
+    )).small().weak());
+
+    // --- Line ranges ---
+    ui.horizontal_wrapped(|ui| {
+        if file.line_ranges.is_empty() {
+            ui.label(egui::RichText::new("Whole file").italics().weak());
+        }
+        let mut remove = None;
+        for (idx, range) in file.line_ranges.iter().enumerate() {
+            if ui.small_button(format!("{}-{} ❌", range.start, range.end)).on_hover_text("Remove range").clicked() {
+                remove = Some(idx);
+            }
+        }
+        if let Some(idx) = remove {
+            file.line_ranges.remove(idx);
+        }
+        if !file.line_ranges.is_empty() && ui.small_button("Clear").clicked() {
+            file.line_ranges.clear();
+        }
+    });
+    ui.label(egui::RichText::new(match preview.anchor {
+        Some(start) => format!("Range starts at line {}. Click the last line number.", start),
+        None => "Click a line number to start a range.".to_string(),
+    }).small().weak());
+    ui.separator();
+
+    match &preview.content {
+        None => {
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                ui.spinner();
+            });
+        }
+        Some(Err(error)) => {
+            ui.colored_label(egui::Color32::RED, format!("Cannot read file: {}", error));
+        }
+        Some(Ok(content)) => {
+            let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            let width = content.lines.len().to_string().len();
+
+            let mut scroll = egui::ScrollArea::both().auto_shrink(false);
+            if let Some(line) = preview.scroll_to.take() {
+                // A few lines of context above the target
+                let spacing = ui.spacing().item_spacing.y;
+                scroll = scroll.vertical_scroll_offset(line.saturating_sub(4) as f32 * (row_height + spacing));
+            }
+            scroll.show_rows(ui, row_height, content.lines.len(), |ui, rows| {
+                for idx in rows {
+                    let number = idx + 1;
+                    ui.horizontal(|ui| {
+                        let in_range = file.line_ranges.iter().any(|r| r.contains(number)) || preview.anchor == Some(number);
+                        let gutter = egui::RichText::new(format!("{:>width$}", number)).monospace().weak();
+                        if ui.selectable_label(in_range, gutter).clicked() {
+                            match preview.anchor.take() {
+                                None => preview.anchor = Some(number),
+                                Some(start) => {
+                                    line_range::add_range(&mut file.line_ranges, LineRange::new(start, number));
+                                    file.selected = true;
+                                }
+                            }
+                        }
+                        if preview.marked_line == Some(number) {
+                            let rect = egui::Rect::from_min_size(ui.cursor().min, egui::vec2(ui.available_width(), row_height));
+                            ui.painter().rect_filled(rect, 0.0, egui::Color32::from_rgba_unmultiplied(255, 200, 0, 40));
+                        }
+                        match content.styled.as_ref().and_then(|styled| styled.get(idx)) {
+                            Some(line) => ui.label(highlight::layout_line(line, &font_id)),
+                            None => ui.label(egui::RichText::new(&content.lines[idx]).monospace()),
+                        };
+                    });
+                }
+            });
+        }
+    }
+
+    let changed = before != (file.selected, file.line_ranges.clone());
+    if changed {
+        app.invalidate_token_estimate();
+    }
+    if close {
+        app.preview = None;
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use eframe::egui;
+use crate::app::CodeCollectorApp;
+
+pub enum RecentAction {
+    Open(PathBuf),
+    TogglePin(PathBuf),
+    Remove(PathBuf),
+}
+
+// "just now", "5 min ago", "3 h ago", "2 d ago"
+pub fn format_age(timestamp: u64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let secs = now.saturating_sub(timestamp);
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86_399 => format!("{} h ago", secs / 3600),
+        _ => format!("{} d ago", secs / 86_400),
+    }
+}
+
+// Shared by the start screen and the top-panel dropdown
+pub fn show_recent_projects_list(ui: &mut egui::Ui, app: &mut CodeCollectorApp) {
+    if app.recent_projects.is_empty() {
+        ui.label(egui::RichText::new("No recent projects").italics().weak());
+        return;
+    }
+
+    let mut action = None;
+    for project in app.sorted_recent_projects() {
+        let exists = project.exists();
+        ui.horizontal(|ui| {
+            let pin = if project.pinned { "📌" } else { "📍" };
+            if ui.small_button(pin).on_hover_text(if project.pinned { "Unpin" } else { "Pin" }).clicked() {
+                action = Some(RecentAction::TogglePin(project.path.clone()));
+            }
+
+            let name = egui::RichText::new(project.name()).strong();
+            let button = ui.add_enabled(exists && !app.is_loading, egui::Button::new(name).frame(false))
+                .on_hover_text(project.path.to_string_lossy());
+            if button.clicked() {
+                action = Some(RecentAction::Open(project.path.clone()));
+                ui.close_menu();
+            }
+
+            if exists {
+                ui.label(egui::RichText::new(format_age(project.last_opened)).small().weak());
+            } else {
+                ui.colored_label(egui::Color32::from_rgb(255, 149, 0), "⚠ missing");
+                if ui.small_button("Remove").clicked() {
+                    action = Some(RecentAction::Remove(project.path.clone()));
+                }
+            }
+        });
+    }
+
+    match action {
+        Some(RecentAction::Open(path)) => app.open_project(path),
+        Some(RecentAction::TogglePin(path)) => app.toggle_recent_pin(&path),
+        Some(RecentAction::Remove(path)) => app.recent_projects.retain(|p| p.path != path),
+        None => {}
+    }
+}
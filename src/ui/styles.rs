@@ -1,4 +1,5 @@
 use eframe::egui;
+use crate::models::git_status::GitStatus;
 
 pub fn get_file_color(extension: &str, ui: &egui::Ui) -> egui::Color32 {
     let is_dark = ui.visuals().dark_mode;
@@ -18,4 +19,13 @@ pub fn get_file_color(extension: &str, ui: &egui::Ui) -> egui::Color32 {
         "blade.php" => egui::Color32::from_rgb(240, 83, 64),
         _ => if is_dark { egui::Color32::LIGHT_GRAY } else { egui::Color32::DARK_GRAY },
     }
-}
\ No newline at end of file
+}
+
+pub fn get_git_status_color(status: GitStatus) -> egui::Color32 {
+    match status {
+        GitStatus::Modified | GitStatus::Renamed => egui::Color32::from_rgb(255, 149, 0),
+        GitStatus::Staged | GitStatus::Added => egui::Color32::from_rgb(52, 199, 89),
+        GitStatus::Untracked => egui::Color32::from_rgb(142, 142, 147),
+        GitStatus::Conflicted => egui::Color32::from_rgb(255, 59, 48),
+    }
+}
@@ -1,72 +1,212 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use eframe::egui;
 use crate::models::{dir_node::DirNode, file_node::FileNode};
-use crate::operations::{selection, search};
-use super::styles::get_file_color;
+use crate::operations::{selection, search, skeleton};
+use crate::operations::search::{FuzzyMatch, SearchMatches};
+use crate::operations::tracking::ExportChanges;
+use super::styles::{get_file_color, get_git_status_color};
 
-pub fn render_tree_main(ui: &mut egui::Ui, dir: &mut DirNode, is_root: bool, query: &str) -> Option<FileNode> {
-    let mut recent_update: Option<FileNode> = None;
+// Read-only state the tree needs for filtering and badges
+pub struct TreeContext<'a> {
+    pub matches: Option<&'a SearchMatches>, // None = no search active
+    pub truncated: &'a HashSet<String>, // Selected files the export cuts short
+    pub previewed: Option<&'a Path>,
+    pub changed_since_export: Option<&'a ExportChanges>,
+}
+
+// Things the tree asks the app to do after rendering
+pub enum TreeAction {
+    Selected(FileNode),
+    OpenPreview(PathBuf),
+    SelectDependencies(String, Option<usize>), // (rel_path, max depth; None = all)
+    SelectDependents(String, Option<usize>),
+    SelectionChanged, // Anything that changes what gets exported (selection, skeleton flag)
+}
 
-    let render_content = |ui: &mut egui::Ui, dir: &mut DirNode, recent_update: &mut Option<FileNode>| {
+pub fn render_tree_main(ui: &mut egui::Ui, dir: &mut DirNode, is_root: bool, ctx: &TreeContext, actions: &mut Vec<TreeAction>) {
+    let render_content = |ui: &mut egui::Ui, dir: &mut DirNode, actions: &mut Vec<TreeAction>| {
         // Subdirectories
         for sub_dir in &mut dir.children_dirs {
-            if search::matches_search(sub_dir, query) {
-                if let Some(node) = render_tree_main(ui, sub_dir, false, query) {
-                    *recent_update = Some(node);
-                }
+            if search::dir_has_match(sub_dir, ctx.matches) {
+                render_tree_main(ui, sub_dir, false, ctx, actions);
             }
         }
         
         // Files
         for file in &mut dir.children_files {
-            if !query.is_empty() && !file.name.to_lowercase().contains(&query.to_lowercase()) { continue; }
+            if !search::is_visible(file, ctx.matches) { continue; }
 
-            ui.horizontal(|ui| {
-                ui.add_space(24.0);
-                if ui.checkbox(&mut file.selected, "").changed() {
-                     if file.selected {
-                        *recent_update = Some(file.clone());
-                     }
-                }
-                
-                let color = get_file_color(&file.extension, ui);
-                if ui.selectable_label(false, egui::RichText::new(&file.name).color(color)).clicked() {
-                    file.selected = !file.selected;
-                    if file.selected {
-                        *recent_update = Some(file.clone());
-                    }
-                }
-            });
+            let label = file_label(file, ctx.matches, false, ui);
+            render_file_row(ui, file, label, ctx, actions);
         }
     };
 
     if is_root {
-        render_content(ui, dir, &mut recent_update);
+        render_content(ui, dir, actions);
     } else {
         ui.horizontal(|ui| {
             let mut is_checked = selection::is_dir_fully_selected(dir);
             if ui.checkbox(&mut is_checked, "").changed() {
-                selection::set_dir_selection(dir, is_checked, query);
+                selection::set_dir_selection(dir, is_checked, ctx.matches);
+                actions.push(TreeAction::SelectionChanged);
             }
 
             let mut header = egui::CollapsingHeader::new(egui::RichText::new(&dir.name).strong()).id_salt(&dir.path);
-            if !query.is_empty() { header = header.default_open(true); }
+            if ctx.matches.is_some() { header = header.default_open(true); }
 
             header.icon(|ui, open, _| {
                 ui.label(egui::RichText::new(if open > 0.0 { "📂" } else { "📁" }).color(egui::Color32::GOLD));
             })
-            .show(ui, |ui| render_content(ui, dir, &mut recent_update));
+            .show(ui, |ui| render_content(ui, dir, actions));
         });
     }
+}
 
-    recent_update
+// One file line: checkbox, name (with context menu) and badges. Shared by the tree and the ranked list.
+fn render_file_row(ui: &mut egui::Ui, file: &mut FileNode, label: egui::text::LayoutJob, ctx: &TreeContext, actions: &mut Vec<TreeAction>) {
+    ui.horizontal(|ui| {
+        ui.add_space(24.0);
+        let mut checked = file.selected;
+        if ui.checkbox(&mut checked, "").changed() {
+            file.set_selected(checked);
+            actions.push(TreeAction::SelectionChanged);
+            if file.selected {
+                actions.push(TreeAction::Selected(file.clone()));
+            }
+        }
+        
+        let is_previewed = ctx.previewed == Some(file.path.as_path());
+        let label = ui.selectable_label(is_previewed, label)
+            .on_hover_text("Click to preview, double-click to toggle selection");
+        if label.double_clicked() {
+            file.set_selected(!file.selected);
+            actions.push(TreeAction::SelectionChanged);
+            if file.selected {
+                actions.push(TreeAction::Selected(file.clone()));
+            }
+        } else if label.clicked() {
+            actions.push(TreeAction::OpenPreview(file.path.clone()));
+        }
+        label.context_menu(|ui| {
+            if ui.button("Preview / select lines").clicked() {
+                actions.push(TreeAction::OpenPreview(file.path.clone()));
+                ui.close_menu();
+            }
+            ui.menu_button("Select with dependencies", |ui| {
+                for (label, depth) in [("Direct imports", Some(1)), ("2 levels", Some(2)), ("3 levels", Some(3)), ("All (transitive)", None)] {
+                    if ui.button(label).clicked() {
+                        actions.push(TreeAction::SelectDependencies(file.rel_path.clone(), depth));
+                        ui.close_menu();
+                    }
+                }
+            });
+            ui.menu_button("Select files that use this", |ui| {
+                for (label, depth) in [("Direct importers", Some(1)), ("All (transitive)", None)] {
+                    if ui.button(label).clicked() {
+                        actions.push(TreeAction::SelectDependents(file.rel_path.clone(), depth));
+                        ui.close_menu();
+                    }
+                }
+            });
+            ui.add_enabled_ui(skeleton::supports(&file.extension), |ui| {
+                if ui.checkbox(&mut file.skeleton, "Signatures only").clicked() {
+                    actions.push(TreeAction::SelectionChanged);
+                    ui.close_menu();
+                }
+            });
+        });
+
+        if let Some(status) = file.git_status {
+            ui.label(egui::RichText::new(status.badge()).small().strong().color(get_git_status_color(status)))
+                .on_hover_text(status.label());
+        }
+        if let Some(changes) = ctx.changed_since_export {
+            let rel_path = selection::normalize_rel_path(&file.rel_path);
+            if changes.modified.contains(&rel_path) {
+                ui.label(egui::RichText::new("Δ").strong().color(egui::Color32::from_rgb(90, 150, 255)))
+                    .on_hover_text("Changed since the last export");
+            } else if changes.added.contains(&rel_path) {
+                ui.label(egui::RichText::new("Δ+").strong().color(egui::Color32::from_rgb(90, 150, 255)))
+                    .on_hover_text("Added since the last export");
+            }
+        }
+        if file.skeleton {
+            ui.label(egui::RichText::new("ƒ").weak()).on_hover_text("Signatures only");
+        }
+        if !file.line_ranges.is_empty() {
+            let ranges = file.line_ranges.iter()
+                .map(|r| format!("{}-{}", r.start, r.end))
+                .collect::<Vec<_>>()
+                .join(", ");
+            ui.label(egui::RichText::new("§").weak()).on_hover_text(format!("Lines {}", ranges));
+        }
+        if file.selected && ctx.truncated.contains(&selection::normalize_rel_path(&file.rel_path)) {
+            ui.label(egui::RichText::new("✂").color(egui::Color32::from_rgb(255, 149, 0)))
+                .on_hover_text("Over the truncation limit — only its head and tail are exported");
+        }
+    });
+}
+
+// File name (or full path) in its language color, with the fuzzy-matched chars highlighted
+fn file_label(file: &FileNode, matches: Option<&SearchMatches>, full_path: bool, ui: &egui::Ui) -> egui::text::LayoutJob {
+    let rel_path = selection::normalize_rel_path(&file.rel_path);
+    let text = if full_path { rel_path.clone() } else { file.name.clone() };
+    // Positions index the rel_path; the name is its tail
+    let offset = rel_path.chars().count().saturating_sub(text.chars().count());
+    let found: Option<&FuzzyMatch> = matches.and_then(|m| m.get(&rel_path));
+
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let normal = egui::TextFormat { font_id: font_id.clone(), color: get_file_color(&file.extension, ui), ..Default::default() };
+    let marked = egui::TextFormat {
+        font_id,
+        color: ui.visuals().strong_text_color(),
+        background: egui::Color32::from_rgba_unmultiplied(255, 200, 0, 70),
+        ..Default::default()
+    };
+    let mut job = egui::text::LayoutJob::default();
+    for (idx, c) in text.chars().enumerate() {
+        let hit = found.is_some_and(|f| f.positions.contains(&(idx + offset)));
+        job.append(c.encode_utf8(&mut [0; 4]), 0.0, if hit { marked.clone() } else { normal.clone() });
+    }
+    job
+}
+
+// Search results as one list, best match first
+pub fn render_ranked_list(ui: &mut egui::Ui, root: &mut DirNode, ctx: &TreeContext, actions: &mut Vec<TreeAction>) {
+    let Some(matches) = ctx.matches else { return; };
+    let mut files = Vec::new();
+    collect_files_mut(root, &mut files);
+    let mut ranked: Vec<(i32, &mut FileNode)> = files.into_iter()
+        .filter_map(|f| matches.get(&selection::normalize_rel_path(&f.rel_path)).map(|m| (m.score, f)))
+        .collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.rel_path.cmp(&b.1.rel_path)));
+
+    if ranked.is_empty() {
+        ui.label(egui::RichText::new("No matching files").italics().weak());
+    }
+    for (_, file) in ranked {
+        let label = file_label(file, ctx.matches, true, ui);
+        render_file_row(ui, file, label, ctx, actions);
+    }
+}
+
+fn collect_files_mut<'a>(dir: &'a mut DirNode, files: &mut Vec<&'a mut FileNode>) {
+    files.extend(dir.children_files.iter_mut());
+    for sub in &mut dir.children_dirs {
+        collect_files_mut(sub, files);
+    }
 }
 
-pub fn render_selected_list(ui: &mut egui::Ui, dir: &mut DirNode) {
+// Returns true when a file was deselected
+pub fn render_selected_list(ui: &mut egui::Ui, dir: &mut DirNode) -> bool {
+    let mut changed = false;
     for file in &mut dir.children_files {
         if file.selected {
             ui.horizontal(|ui| {
                 if ui.button("❌").on_hover_text("Deselect").clicked() {
-                    file.selected = false;
+                    file.set_selected(false);
+                    changed = true;
                 }
                 let color = get_file_color(&file.extension, ui);
                 ui.label(egui::RichText::new(&file.name).color(color));
@@ -74,6 +214,7 @@ pub fn render_selected_list(ui: &mut egui::Ui, dir: &mut DirNode) {
         }
     }
     for sub in &mut dir.children_dirs {
-        render_selected_list(ui, sub);
+        changed |= render_selected_list(ui, sub);
     }
+    changed
 }
\ No newline at end of file